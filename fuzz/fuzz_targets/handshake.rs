@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use signalr_rs::protocol;
+
+// The first frame after the handshake request, possibly followed by more records.
+fuzz_target!(|payload: &str| {
+    let _ = protocol::parse_handshake_response(payload);
+});
@@ -0,0 +1,9 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use signalr_rs::protocol;
+
+// The body of a /negotiate response.
+fuzz_target!(|body: &str| {
+    let _ = protocol::parse_negotiation(body);
+});
@@ -0,0 +1,11 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use signalr_rs::protocol;
+
+// A text frame as received from the server: records split on the record separator.
+fuzz_target!(|payload: &str| {
+    for message in protocol::parse_messages(payload) {
+        let _ = message.serialize();
+    }
+});
@@ -0,0 +1,53 @@
+// Writes the golden files of serializer_test/tests/golden with the .NET JsonHubProtocol.
+//
+//     dotnet run --project serializer_test/dotnet -- serializer_test/tests/golden
+
+using System.Buffers;
+using System.Text;
+using Microsoft.AspNetCore.SignalR.Protocol;
+
+var directory = args.Length > 0 ? args[0] : Path.Combine("..", "tests", "golden");
+var protocol = new JsonHubProtocol();
+
+HubMessage WithHeaders(HubInvocationMessage message, string name, string value)
+{
+    message.Headers = new Dictionary<string, string> { [name] = value };
+    return message;
+}
+
+var messages = new Dictionary<string, HubMessage>
+{
+    ["invocation"] = new InvocationMessage("SendMessage", new object?[] { "rust", "Hello" }),
+    ["invocation_with_id"] = new InvocationMessage("0", "Add", new object?[] { 1, 2 }),
+    ["invocation_nested_arguments"] = new InvocationMessage("12", "Update", new object?[]
+    {
+        new { id = 7, owner = (string?)null, tags = new[] { "a", "b" } },
+        new object[] { 1.5, -2, true },
+    }),
+    ["invocation_headers_stream_ids"] = WithHeaders(
+        new InvocationMessage("5", "Upload", new object?[] { "photos" }, new[] { "0" }),
+        "traceparent",
+        "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01"),
+    ["stream_item"] = new StreamItemMessage("1", new { count = 3 }),
+    ["completion_result"] = CompletionMessage.WithResult("0", 3),
+    ["completion_null_result"] = CompletionMessage.WithResult("3", null),
+    ["completion_void"] = CompletionMessage.Empty("2"),
+    ["completion_error"] = CompletionMessage.WithError("4", "Invalid credentials."),
+    ["stream_invocation"] = new StreamInvocationMessage("1", "Counter", new object?[] { 10, 500 }),
+    ["cancel_invocation"] = new CancelInvocationMessage("1"),
+    ["cancel_invocation_headers"] = WithHeaders(new CancelInvocationMessage("6"), "a", "1"),
+    ["ping"] = PingMessage.Instance,
+    ["close"] = CloseMessage.Empty,
+    ["close_allow_reconnect"] = new CloseMessage(null, allowReconnect: true),
+    ["close_with_error"] = new CloseMessage("Connection closed with an error.", allowReconnect: true),
+};
+
+Directory.CreateDirectory(directory);
+foreach (var (name, message) in messages)
+{
+    var buffer = new ArrayBufferWriter<byte>();
+    protocol.WriteMessage(message, buffer);
+    // One message per file, the record separator replaced by a newline.
+    var json = Encoding.UTF8.GetString(buffer.WrittenSpan).TrimEnd('\u001e');
+    File.WriteAllText(Path.Combine(directory, name + ".json"), json + "\n");
+}
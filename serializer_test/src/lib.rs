@@ -0,0 +1,109 @@
+//! Wire format tests for the JSON hub protocol of `signalr-rs`.
+//!
+//! The strategies below generate messages as the JSON the protocol puts on the wire, in the
+//! canonical form the client serializes them back to; the tests live in `tests/`.
+
+use proptest::{collection, option, prelude::*};
+use serde_json::{Map, Value};
+
+/// Any JSON value, nested up to a few levels, as carried by arguments, items and results.
+pub fn json_value() -> impl Strategy<Value = Value> {
+    let leaf = prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::from),
+        any::<i64>().prop_map(Value::from),
+        any::<u64>().prop_map(Value::from),
+        any::<f64>().prop_filter("JSON numbers are finite", |n| n.is_finite()).prop_map(Value::from),
+        any::<String>().prop_map(Value::from),
+    ];
+    leaf.prop_recursive(4, 32, 6, |inner| {
+        prop_oneof![
+            collection::vec(inner.clone(), 0..6).prop_map(Value::Array),
+            collection::btree_map(any::<String>(), inner, 0..6).prop_map(|map| Value::Object(map.into_iter().collect())),
+        ]
+    })
+}
+
+fn invocation_id() -> impl Strategy<Value = String> {
+    prop_oneof![any::<u32>().prop_map(|id| id.to_string()), any::<String>()]
+}
+
+fn arguments() -> impl Strategy<Value = Value> {
+    collection::vec(json_value(), 0..5).prop_map(Value::Array)
+}
+
+/// Builds a message object, leaving out the fields that are `None`.
+fn message(message_type: u64, fields: Vec<(&'static str, Option<Value>)>) -> Value {
+    let mut object = Map::new();
+    object.insert("type".to_owned(), Value::from(message_type));
+    for (name, value) in fields {
+        if let Some(value) = value {
+            object.insert(name.to_owned(), value);
+        }
+    }
+    Value::Object(object)
+}
+
+fn headers() -> impl Strategy<Value = Option<Value>> {
+    collection::btree_map(any::<String>(), any::<String>(), 0..3)
+        .prop_map(|headers| (!headers.is_empty()).then(|| Value::Object(headers.into_iter().map(|(k, v)| (k, Value::from(v))).collect())))
+}
+
+fn stream_ids() -> impl Strategy<Value = Option<Value>> {
+    collection::vec(invocation_id(), 0..3).prop_map(|ids| (!ids.is_empty()).then(|| Value::from(ids)))
+}
+
+/// Any message of the JSON hub protocol, every variant and optional field included.
+pub fn json_message() -> impl Strategy<Value = Value> {
+    prop_oneof![
+        (headers(), option::of(invocation_id()), any::<String>(), arguments(), stream_ids()).prop_map(
+            |(headers, id, target, arguments, stream_ids)| {
+                message(1, vec![
+                    ("headers", headers),
+                    ("invocationId", id.map(Value::from)),
+                    ("target", Some(Value::from(target))),
+                    ("arguments", Some(arguments)),
+                    ("streamIds", stream_ids),
+                ])
+            }
+        ),
+        (headers(), invocation_id(), json_value()).prop_map(|(headers, id, item)| {
+            message(2, vec![
+                ("headers", headers),
+                ("invocationId", Some(Value::from(id))),
+                ("item", Some(item)),
+            ])
+        }),
+        (headers(), invocation_id(), option::of(any::<String>()), option::of(json_value())).prop_map(
+            |(headers, id, error, result)| {
+                message(3, vec![
+                    ("headers", headers),
+                    ("invocationId", Some(Value::from(id))),
+                    ("error", error.map(Value::from)),
+                    ("result", result),
+                ])
+            }
+        ),
+        (headers(), invocation_id(), any::<String>(), arguments(), stream_ids()).prop_map(
+            |(headers, id, target, arguments, stream_ids)| {
+                message(4, vec![
+                    ("headers", headers),
+                    ("invocationId", Some(Value::from(id))),
+                    ("target", Some(Value::from(target))),
+                    ("arguments", Some(arguments)),
+                    ("streamIds", stream_ids),
+                ])
+            }
+        ),
+        (headers(), invocation_id()).prop_map(|(headers, id)| {
+            message(5, vec![("headers", headers), ("invocationId", Some(Value::from(id)))])
+        }),
+        Just(message(6, Vec::new())),
+        (option::of(any::<String>()), any::<bool>()).prop_map(|(error, allow_reconnect)| {
+            message(7, vec![
+                ("error", error.map(Value::from)),
+                ("allowReconnect", allow_reconnect.then_some(Value::Bool(true))),
+            ])
+        }),
+    ]
+}
@@ -0,0 +1,38 @@
+//! Messages as the .NET `JsonHubProtocol` writes them, one per file; the serializer must
+//! reproduce them byte for byte. Nested objects are compared with their keys sorted.
+//!
+//! The files are written by the generator in `dotnet/` on the `JsonHubProtocol` of
+//! Microsoft.AspNetCore.SignalR.Protocols.Json, run it to refresh them after adding a message:
+//! `dotnet run --project serializer_test/dotnet -- serializer_test/tests/golden`.
+
+use std::{collections::BTreeSet, fs, path::Path};
+
+use serde_json::Value;
+
+use signalr_rs::protocol::responses::Messsage;
+
+const RECORD_SEPARATOR: char = '\u{1e}';
+
+#[test]
+fn matches_golden_files() {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    let mut types = BTreeSet::new();
+    let mut close_allowing_reconnect = false;
+    for entry in fs::read_dir(&directory).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().is_none_or(|extension| extension != "json") {
+            continue;
+        }
+        let golden = fs::read_to_string(&path).unwrap();
+        let golden = golden.trim_end();
+        let message = Messsage::deserialize(golden).unwrap_or_else(|| panic!("{} does not parse", path.display()));
+        let record = message.serialize().unwrap();
+        assert_eq!(record.trim_end_matches(RECORD_SEPARATOR), golden, "{}", path.display());
+
+        let golden: Value = serde_json::from_str(golden).unwrap();
+        types.insert(golden["type"].as_u64().unwrap());
+        close_allowing_reconnect |= golden["type"] == 7 && golden["allowReconnect"] == true;
+    }
+    assert_eq!(types, (1..=7).collect(), "every message type needs a golden file");
+    assert!(close_allowing_reconnect, "a Close message allowing reconnects needs a golden file");
+}
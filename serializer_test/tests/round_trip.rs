@@ -0,0 +1,26 @@
+use proptest::prelude::*;
+use serde_json::Value;
+use serializer_test::json_message;
+use signalr_rs::protocol::{self, responses::Messsage};
+
+const RECORD_SEPARATOR: char = '\u{1e}';
+
+proptest! {
+    #[test]
+    fn messages_round_trip(json in json_message()) {
+        let message = Messsage::deserialize(&json.to_string()).expect("generated message must parse");
+        let record = message.clone().serialize().expect("parsed message must serialize");
+        prop_assert!(record.ends_with(RECORD_SEPARATOR));
+
+        let written: Value = serde_json::from_str(record.trim_end_matches(RECORD_SEPARATOR)).unwrap();
+        prop_assert_eq!(&written, &json);
+        let parsed: Vec<_> = protocol::parse_messages(&record).collect();
+        prop_assert_eq!(parsed, vec![message]);
+    }
+
+    #[test]
+    fn frames_split_into_their_messages(messages in proptest::collection::vec(json_message(), 1..8)) {
+        let frame: String = messages.iter().map(|message| format!("{}{}", message, RECORD_SEPARATOR)).collect();
+        prop_assert_eq!(protocol::parse_messages(&frame).count(), messages.len());
+    }
+}
@@ -0,0 +1,155 @@
+use std::{collections::HashSet, fmt::Write};
+
+use crate::{
+    model::{Definition, Dto, Hub, Method},
+    naming::{identifier, to_pascal_case, type_name},
+    types::rust_type,
+    Error,
+};
+
+const HEADER: &str = "// @generated by signalr-rs-codegen, do not edit by hand.\n";
+
+pub fn generate(definition: &Definition) -> Result<String, Error> {
+    let dtos: HashSet<&str> = definition.types.iter().map(|dto| dto.name.as_str()).collect();
+    let mut source = String::from(HEADER);
+    for dto in &definition.types {
+        source.push('\n');
+        write_dto(&mut source, dto, &dtos)?;
+    }
+    for hub in &definition.hubs {
+        write_hub(&mut source, hub, &dtos)?;
+    }
+    Ok(source)
+}
+
+fn write_dto(source: &mut String, dto: &Dto, dtos: &HashSet<&str>) -> Result<(), Error> {
+    let name = type_name(&dto.name)?;
+    source.push_str("#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]\n");
+    writeln!(source, "pub struct {} {{", name).unwrap();
+    for property in &dto.properties {
+        let context = |e: Error| Error::new(format!("{} on {}.{}", e, dto.name, property.name));
+        let ty = rust_type(&property.ty, dtos).map_err(context)?;
+        let field = identifier(&property.name).map_err(context)?;
+        if field.trim_start_matches("r#") != property.name {
+            writeln!(source, "    #[serde(rename = {:?})]", property.name).unwrap();
+        }
+        writeln!(source, "    pub {}: {},", field, ty).unwrap();
+    }
+    source.push_str("}\n");
+    Ok(())
+}
+
+fn write_hub(source: &mut String, hub: &Hub, dtos: &HashSet<&str>) -> Result<(), Error> {
+    let hub_name = type_name(&hub.name)?;
+    if !hub.methods.is_empty() {
+        writeln!(source, "\n#[::signalr_rs::hub_client]\npub trait {} {{", hub_name).unwrap();
+        for method in &hub.methods {
+            let context = |e: Error| Error::new(format!("{} on {}.{}", e, hub.name, method.name));
+            let parameters = parameters(hub, method, dtos)?;
+            let returns = match method.returns.as_deref() {
+                None | Some("void" | "Task") => "()".to_owned(),
+                Some(ty) => rust_type(ty, dtos).map_err(context)?,
+            };
+            let name = identifier(&method.name).map_err(context)?;
+            write_name(source, method, &name);
+            if method.stream {
+                writeln!(
+                    source,
+                    "    fn {}(&self{}) -> impl ::signalr_rs::Stream<Item = ::signalr_rs::Result<{}>>;",
+                    name, parameters, returns
+                )
+                .unwrap();
+            } else {
+                writeln!(source, "    async fn {}(&self{}) -> ::signalr_rs::Result<{}>;", name, parameters, returns).unwrap();
+            }
+        }
+        source.push_str("}\n");
+    }
+
+    if !hub.client_methods.is_empty() {
+        writeln!(source, "\n#[::signalr_rs::hub_receiver]\npub trait {}Receiver {{", hub_name).unwrap();
+        for method in &hub.client_methods {
+            if !matches!(method.returns.as_deref(), None | Some("void" | "Task")) || method.stream {
+                return Err(Error::new(format!(
+                    "Client method {}.{} cannot return a value",
+                    hub.name, method.name
+                )));
+            }
+            let parameters = parameters(hub, method, dtos)?;
+            let name = identifier(&method.name).map_err(|e| Error::new(format!("{} on {}.{}", e, hub.name, method.name)))?;
+            write_name(source, method, &name);
+            writeln!(source, "    async fn {}(&self{});", name, parameters).unwrap();
+        }
+        source.push_str("}\n");
+    }
+    Ok(())
+}
+
+/// Pins the hub method name when the macros would not derive it from the Rust identifier.
+fn write_name(source: &mut String, method: &Method, name: &str) {
+    if to_pascal_case(name.trim_start_matches("r#")) != method.name {
+        writeln!(source, "    #[hub(name = {:?})]", method.name).unwrap();
+    }
+}
+
+fn parameters(hub: &Hub, method: &Method, dtos: &HashSet<&str>) -> Result<String, Error> {
+    let mut parameters = String::new();
+    for parameter in &method.parameters {
+        let context = |e: Error| Error::new(format!("{} on parameter {} of {}.{}", e, parameter.name, hub.name, method.name));
+        let ty = rust_type(&parameter.ty, dtos).map_err(context)?;
+        write!(parameters, ", {}: {}", identifier(&parameter.name).map_err(context)?, ty).unwrap();
+    }
+    Ok(parameters)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::generate;
+
+    #[test]
+    fn generates_dtos_and_hub_traits() {
+        let source = generate(
+            r#"{
+                "hubs": [{
+                    "name": "ChatHub",
+                    "methods": [
+                        { "name": "SendMessage", "parameters": [{ "name": "message", "type": "ChatMessage" }] },
+                        { "name": "getHTTPStatus", "returns": "int" },
+                        { "name": "Counter", "parameters": [{ "name": "count", "type": "int" }], "returns": "int", "stream": true }
+                    ],
+                    "clientMethods": [
+                        { "name": "ReceiveMessage", "parameters": [{ "name": "message", "type": "ChatMessage" }] }
+                    ]
+                }],
+                "types": [{
+                    "name": "ChatMessage",
+                    "properties": [{ "name": "sentAt", "type": "DateTime?" }, { "name": "type", "type": "string" }]
+                }]
+            }"#,
+        )
+        .unwrap();
+
+        assert!(source.contains("    #[serde(rename = \"sentAt\")]\n    pub sent_at: Option<String>,\n"));
+        assert!(source.contains("    pub r#type: String,\n"));
+        assert!(source.contains("    async fn send_message(&self, message: ChatMessage) -> ::signalr_rs::Result<()>;\n"));
+        assert!(source.contains("    #[hub(name = \"getHTTPStatus\")]\n    async fn get_http_status(&self) -> ::signalr_rs::Result<i32>;\n"));
+        assert!(source.contains("    fn counter(&self, count: i32) -> impl ::signalr_rs::Stream<Item = ::signalr_rs::Result<i32>>;\n"));
+        assert!(source.contains("pub trait ChatHubReceiver {\n    async fn receive_message(&self, message: ChatMessage);\n}\n"));
+    }
+
+    #[test]
+    fn rejects_unknown_types() {
+        let error = generate(r#"{ "hubs": [{ "name": "Hub", "methods": [{ "name": "Get", "returns": "Missing" }] }] }"#)
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Unknown type Missing on Hub.Get");
+    }
+
+    #[test]
+    fn rejects_names_that_are_not_identifiers() {
+        let error = generate(r#"{ "hubs": [{ "name": "Chat Hub", "methods": [{ "name": "Get" }] }] }"#).unwrap_err();
+        assert_eq!(error.to_string(), "Invalid type name \"Chat Hub\"");
+        let error = generate(r#"{ "types": [{ "name": "Message", "properties": [{ "name": "$", "type": "int" }] }] }"#)
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Invalid name \"$\" on Message.$");
+    }
+}
@@ -0,0 +1,83 @@
+//! Generates `signalr-rs` typed hub proxies from a JSON description of ASP.NET Core hubs.
+//!
+//! The description lists the hubs with the methods clients call on them (`methods`), the
+//! methods the hub calls on its clients (`clientMethods`) and the DTOs both use (`types`):
+//!
+//! ```json
+//! {
+//!   "hubs": [{
+//!     "name": "ChatHub",
+//!     "methods": [
+//!       { "name": "SendMessage", "parameters": [{ "name": "message", "type": "ChatMessage" }] },
+//!       { "name": "Counter", "parameters": [{ "name": "count", "type": "int" }], "returns": "int", "stream": true }
+//!     ],
+//!     "clientMethods": [
+//!       { "name": "ReceiveMessage", "parameters": [{ "name": "message", "type": "ChatMessage" }] }
+//!     ]
+//!   }],
+//!   "types": [{
+//!     "name": "ChatMessage",
+//!     "properties": [{ "name": "user", "type": "string" }, { "name": "sentAt", "type": "DateTime?" }]
+//!   }]
+//! }
+//! ```
+//!
+//! Every hub becomes a `#[hub_client]` trait named after it plus a `#[hub_receiver]` trait
+//! suffixed with `Receiver`, every DTO a serde struct keeping the wire names of its properties.
+//! Types use C# or TypeScript spellings: `string`, `int`, `long`, `double`, `number`, `bool`,
+//! `Guid`, `DateTime`, `object`, `T[]`, `List<T>`, `Dictionary<string, T>`, `T?` and DTO names.
+//! The generated code needs `serde` and `serde_json` next to `signalr-rs`.
+//!
+//! From a build script:
+//!
+//! ```no_run
+//! let out_dir = std::env::var("OUT_DIR").unwrap();
+//! signalr_rs_codegen::generate_file("hubs.json", format!("{}/hubs.rs", out_dir)).unwrap();
+//! ```
+//!
+//! then `include!(concat!(env!("OUT_DIR"), "/hubs.rs"));` in the crate.
+
+use std::{fmt, fs, path::Path};
+
+mod generator;
+mod model;
+mod naming;
+mod types;
+
+pub use model::{Definition, Dto, Hub, Method, Parameter};
+
+#[derive(Debug)]
+pub struct Error {
+    message: String,
+}
+
+impl Error {
+    pub(crate) fn new(message: String) -> Self {
+        Error { message }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Generates the Rust source for the JSON hub description in `definition`.
+pub fn generate(definition: &str) -> Result<String, Error> {
+    let definition = serde_json::from_str::<Definition>(definition)
+        .map_err(|e| Error::new(format!("Invalid hub description, inner {}", e)))?;
+    generator::generate(&definition)
+}
+
+/// Reads the description at `input` and writes the generated source to `output`.
+pub fn generate_file<I: AsRef<Path>, O: AsRef<Path>>(input: I, output: O) -> Result<(), Error> {
+    let input = input.as_ref();
+    let definition = fs::read_to_string(input)
+        .map_err(|e| Error::new(format!("Failed to read {}, inner {}", input.display(), e)))?;
+    let source = generate(&definition)?;
+    let output = output.as_ref();
+    fs::write(output, source).map_err(|e| Error::new(format!("Failed to write {}, inner {}", output.display(), e)))
+}
@@ -0,0 +1,21 @@
+use std::{env, fs, process};
+
+fn main() {
+    let arguments: Vec<String> = env::args().skip(1).collect();
+    if arguments.is_empty() || arguments.len() > 2 {
+        eprintln!("usage: signalr-rs-codegen <hubs.json> [output.rs]");
+        process::exit(2);
+    }
+
+    let result = match arguments.get(1) {
+        Some(output) => signalr_rs_codegen::generate_file(&arguments[0], output).map_err(|e| e.to_string()),
+        None => fs::read_to_string(&arguments[0])
+            .map_err(|e| format!("Failed to read {}, inner {}", arguments[0], e))
+            .and_then(|definition| signalr_rs_codegen::generate(&definition).map_err(|e| e.to_string()))
+            .map(|source| print!("{}", source)),
+    };
+    if let Err(error) = result {
+        eprintln!("{}", error);
+        process::exit(1);
+    }
+}
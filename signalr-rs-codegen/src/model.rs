@@ -0,0 +1,48 @@
+use serde::Deserialize;
+
+/// Root of the hub description consumed by the generator.
+#[derive(Deserialize, Debug)]
+pub struct Definition {
+    #[serde(default)]
+    pub hubs: Vec<Hub>,
+    #[serde(default)]
+    pub types: Vec<Dto>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Hub {
+    pub name: String,
+    /// Methods the client calls on the server.
+    #[serde(default)]
+    pub methods: Vec<Method>,
+    /// Methods the server calls on the client.
+    #[serde(default, rename = "clientMethods")]
+    pub client_methods: Vec<Method>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Method {
+    pub name: String,
+    #[serde(default)]
+    pub parameters: Vec<Parameter>,
+    /// Type of the result, `void` or missing when the method returns nothing.
+    #[serde(default)]
+    pub returns: Option<String>,
+    /// The method streams items of `returns` instead of returning a single value.
+    #[serde(default)]
+    pub stream: bool,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Parameter {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Dto {
+    pub name: String,
+    #[serde(default)]
+    pub properties: Vec<Parameter>,
+}
@@ -0,0 +1,114 @@
+use crate::Error;
+
+/// Rust keywords that have to be written as raw identifiers.
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn", "else",
+    "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in", "let", "loop", "macro",
+    "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return", "static", "struct", "trait",
+    "true", "try", "type", "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// `SendMessage`, `sendMessage` and `GetHTTPStatus` become `send_message` and `get_http_status`.
+pub fn to_snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut snake = String::with_capacity(name.len() + 4);
+    for (i, &c) in chars.iter().enumerate() {
+        if !c.is_alphanumeric() {
+            if !snake.is_empty() && !snake.ends_with('_') {
+                snake.push('_');
+            }
+            continue;
+        }
+        if c.is_uppercase() && i > 0 && !snake.ends_with('_') {
+            let previous = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).is_some_and(|next| next.is_lowercase());
+            if previous.is_lowercase() || previous.is_ascii_digit() || (previous.is_uppercase() && next_is_lower) {
+                snake.push('_');
+            }
+        }
+        snake.extend(c.to_lowercase());
+    }
+    snake.trim_end_matches('_').to_owned()
+}
+
+/// Mirrors the name `#[hub_client]` and `#[hub_receiver]` derive from a method identifier,
+/// a copy of `signalr-rs-macro`'s own that the tests of both pin to the same cases.
+pub fn to_pascal_case(name: &str) -> String {
+    name.split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+/// A snake case identifier for `name`, raw when it collides with a keyword.
+pub fn identifier(name: &str) -> Result<String, Error> {
+    let snake = to_snake_case(name);
+    let snake = match snake.chars().next() {
+        Some(first) if first.is_ascii_digit() => format!("_{}", snake),
+        None => return Err(Error::new(format!("Invalid name {:?}", name))),
+        _ => snake,
+    };
+    Ok(match snake.as_str() {
+        "self" | "super" | "crate" | "Self" => format!("{}_", snake),
+        keyword if KEYWORDS.contains(&keyword) => format!("r#{}", snake),
+        _ => snake,
+    })
+}
+
+/// Checks a hub or DTO name can be used as is for the generated trait or struct.
+pub fn type_name(name: &str) -> Result<&str, Error> {
+    let mut chars = name.chars();
+    let valid = chars.next().is_some_and(|first| first.is_alphabetic() || first == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+        && name != "_"
+        && !KEYWORDS.contains(&name)
+        && !matches!(name, "self" | "super" | "crate" | "Self");
+    if valid {
+        Ok(name)
+    } else {
+        Err(Error::new(format!("Invalid type name {:?}", name)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{identifier, to_pascal_case, to_snake_case, type_name};
+
+    #[test]
+    fn converts_hub_names_to_snake_case() {
+        assert_eq!(to_snake_case("SendMessage"), "send_message");
+        assert_eq!(to_snake_case("sendMessage"), "send_message");
+        assert_eq!(to_snake_case("GetHTTPStatus"), "get_http_status");
+        assert_eq!(to_snake_case("Utf8Name"), "utf8_name");
+        assert_eq!(to_snake_case("user-id"), "user_id");
+        assert_eq!(identifier("Type").unwrap(), "r#type");
+        assert_eq!(identifier("self").unwrap(), "self_");
+        assert!(identifier("--").is_err());
+    }
+
+    /// The same cases as the copy in `signalr-rs-macro`, the two have to agree.
+    #[test]
+    fn converts_snake_case_to_pascal_case() {
+        assert_eq!(to_pascal_case("send_message"), "SendMessage");
+        assert_eq!(to_pascal_case("stream_counter"), "StreamCounter");
+        assert_eq!(to_pascal_case("echo"), "Echo");
+        assert_eq!(to_pascal_case("__private_call"), "PrivateCall");
+        assert_eq!(to_pascal_case("get_http_status"), "GetHttpStatus");
+        assert_eq!(to_pascal_case("utf8_name"), "Utf8Name");
+    }
+
+    #[test]
+    fn checks_type_names() {
+        assert_eq!(type_name("ChatHub").unwrap(), "ChatHub");
+        assert!(type_name("Chat-Hub").is_err());
+        assert!(type_name("1Hub").is_err());
+        assert!(type_name("Self").is_err());
+        assert!(type_name("").is_err());
+    }
+}
@@ -0,0 +1,89 @@
+use std::collections::HashSet;
+
+use crate::Error;
+
+/// Maps a C# or TypeScript type name to the Rust type used in the generated code.
+pub fn rust_type(ty: &str, dtos: &HashSet<&str>) -> Result<String, Error> {
+    let ty = ty.trim();
+    if let Some(inner) = ty.strip_suffix('?') {
+        return Ok(format!("Option<{}>", rust_type(inner, dtos)?));
+    }
+    if let Some(inner) = ty.strip_suffix("[]") {
+        return Ok(format!("Vec<{}>", rust_type(inner, dtos)?));
+    }
+    if let Some((name, arguments)) = generic(ty) {
+        return match (name, arguments.as_slice()) {
+            ("List" | "IList" | "IEnumerable" | "ICollection" | "IReadOnlyList" | "IReadOnlyCollection" | "Array", [item]) => {
+                Ok(format!("Vec<{}>", rust_type(item, dtos)?))
+            }
+            ("Dictionary" | "IDictionary" | "IReadOnlyDictionary" | "Record", [_, value]) => Ok(format!(
+                "::std::collections::HashMap<String, {}>",
+                rust_type(value, dtos)?
+            )),
+            ("Nullable", [inner]) => Ok(format!("Option<{}>", rust_type(inner, dtos)?)),
+            _ => Err(Error::new(format!("Unsupported generic type {}", ty))),
+        };
+    }
+
+    let mapped = match ty {
+        "string" | "String" | "Guid" | "DateTime" | "DateTimeOffset" | "TimeSpan" | "Uri" | "Date" => "String",
+        "char" | "Char" => "char",
+        "bool" | "boolean" | "Boolean" => "bool",
+        "byte" | "Byte" => "u8",
+        "sbyte" | "SByte" => "i8",
+        "short" | "Int16" => "i16",
+        "ushort" | "UInt16" => "u16",
+        "int" | "Int32" | "integer" => "i32",
+        "uint" | "UInt32" => "u32",
+        "long" | "Int64" => "i64",
+        "ulong" | "UInt64" => "u64",
+        "float" | "Single" => "f32",
+        "double" | "Double" | "decimal" | "Decimal" | "number" => "f64",
+        "object" | "Object" | "any" | "unknown" | "JsonElement" => "serde_json::Value",
+        dto if dtos.contains(dto) => dto,
+        _ => return Err(Error::new(format!("Unknown type {}", ty))),
+    };
+    Ok(mapped.to_owned())
+}
+
+/// Splits `Name<A, B<C, D>>` into `Name` and its top level type arguments.
+fn generic(ty: &str) -> Option<(&str, Vec<&str>)> {
+    let open = ty.find('<')?;
+    let inner = ty.strip_suffix('>')?.get(open + 1..)?;
+    let mut arguments = Vec::new();
+    let (mut depth, mut start) = (0usize, 0);
+    for (i, c) in inner.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => depth = depth.checked_sub(1)?,
+            ',' if depth == 0 => {
+                arguments.push(inner[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    arguments.push(inner[start..].trim());
+    Some((ty[..open].trim(), arguments))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::rust_type;
+
+    #[test]
+    fn maps_dotnet_and_typescript_types() {
+        let dtos: HashSet<&str> = ["ChatMessage"].into_iter().collect();
+        assert_eq!(rust_type("int", &dtos).unwrap(), "i32");
+        assert_eq!(rust_type("number[]", &dtos).unwrap(), "Vec<f64>");
+        assert_eq!(rust_type("DateTime?", &dtos).unwrap(), "Option<String>");
+        assert_eq!(rust_type("List<ChatMessage>", &dtos).unwrap(), "Vec<ChatMessage>");
+        assert_eq!(
+            rust_type("Dictionary<string, List<int>>", &dtos).unwrap(),
+            "::std::collections::HashMap<String, Vec<i32>>"
+        );
+        assert!(rust_type("Unknown", &dtos).is_err());
+    }
+}
@@ -0,0 +1,28 @@
+// @generated by signalr-rs-codegen, do not edit by hand.
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ChatMessage {
+    pub user: String,
+    #[serde(rename = "sentAt")]
+    pub sent_at: Option<String>,
+    pub r#type: String,
+    pub extra: serde_json::Value,
+}
+
+#[::signalr_rs::hub_client]
+pub trait ChatHub {
+    async fn send_message(&self, message: ChatMessage) -> ::signalr_rs::Result<()>;
+    #[hub(name = "getHTTPStatus")]
+    async fn get_http_status(&self) -> ::signalr_rs::Result<i32>;
+    async fn r#type(&self, r#type: String) -> ::signalr_rs::Result<String>;
+    async fn history(&self, rooms: ::std::collections::HashMap<String, Vec<i32>>) -> ::signalr_rs::Result<Vec<ChatMessage>>;
+    fn counter(&self, count: i32) -> impl ::signalr_rs::Stream<Item = ::signalr_rs::Result<i32>>;
+}
+
+#[::signalr_rs::hub_receiver]
+pub trait ChatHubReceiver {
+    async fn receive_message(&self, message: ChatMessage);
+    #[hub(name = "user-left")]
+    async fn user_left(&self, user: String, at: Option<String>);
+    async fn r#type(&self, r#type: String);
+}
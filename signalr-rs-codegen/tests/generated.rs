@@ -0,0 +1,78 @@
+//! Compiles the checked-in output of `tests/fixtures/chat.json`, regenerate it with
+//! `cargo run -p signalr-rs-codegen -- tests/fixtures/chat.json tests/fixtures/chat.rs`.
+
+use std::sync::{Arc, Mutex};
+
+use serde_json::json;
+use signalr_rs::{testing::MockHubServer, HubConnectionBuilder, HubReceiver};
+
+// Generated code is a public API, the test does not call all of it.
+#[allow(dead_code)]
+mod chat {
+    include!("fixtures/chat.rs");
+}
+
+use chat::{ChatHub, ChatHubReceiver, ChatMessage};
+
+#[test]
+fn checked_in_output_is_up_to_date() {
+    let source = signalr_rs_codegen::generate(include_str!("fixtures/chat.json")).unwrap();
+    assert_eq!(source, include_str!("fixtures/chat.rs"));
+}
+
+#[derive(Default)]
+struct Recorder {
+    calls: Mutex<Vec<String>>,
+}
+
+#[signalr_rs::hub_receiver]
+impl ChatHubReceiver for Recorder {
+    async fn receive_message(&self, message: ChatMessage) {
+        self.calls.lock().unwrap().push(message.user);
+    }
+
+    async fn user_left(&self, user: String, _at: Option<String>) {
+        self.calls.lock().unwrap().push(format!("{} left", user));
+    }
+
+    async fn r#type(&self, r#type: String) {
+        self.calls.lock().unwrap().push(format!("type {}", r#type));
+    }
+}
+
+#[tokio::test]
+async fn generated_traits_use_the_hub_names() {
+    let recorder = Arc::new(Recorder::default());
+    let handlers = recorder.clone().handlers();
+    let targets: Vec<_> = handlers.iter().map(|handler| handler.target()).collect();
+    assert_eq!(targets, vec!["ReceiveMessage", "user-left", "Type"]);
+    handlers[1].call(vec![json!("rust"), json!(null)]).unwrap().await;
+    handlers[2].call(vec![json!("text")]).unwrap().await;
+    assert_eq!(*recorder.calls.lock().unwrap(), vec!["rust left", "type text"]);
+
+    let mut server = MockHubServer::start().await.unwrap();
+    let connection = HubConnectionBuilder::new().with_url(server.url()).build().unwrap();
+    let (started, mut session) = tokio::join!(connection.start(), server.accept());
+    started.unwrap();
+
+    let (result, ()) = tokio::join!(connection.get_http_status(), async {
+        let invocation = session.expect_invocation("getHTTPStatus").await;
+        session.complete(&invocation, json!(200));
+    });
+    assert_eq!(result.unwrap(), 200);
+    let (result, ()) = tokio::join!(connection.r#type("text".to_owned()), async {
+        let invocation = session.expect_invocation("Type").await;
+        assert_eq!(invocation.arguments, vec![json!("text")]);
+        session.complete(&invocation, json!("plain"));
+    });
+    assert_eq!(result.unwrap(), "plain");
+
+    let message = json!({ "user": "rust", "sentAt": null, "type": "text", "extra": {} });
+    let (result, ()) = tokio::join!(connection.history(Default::default()), async {
+        let invocation = session.expect_invocation("History").await;
+        session.complete(&invocation, json!([message]));
+    });
+    assert_eq!(result.unwrap()[0].r#type, "text");
+
+    connection.stop().await.unwrap();
+}
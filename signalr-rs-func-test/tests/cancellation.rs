@@ -0,0 +1,81 @@
+mod common;
+
+use std::time::Duration;
+
+use futures_util::StreamExt;
+use serde_json::json;
+use signalr_rs::{error::ErrorKind, CancellationToken, HubConnectionBuilder, InvocationOptions, OutboundPolicy};
+
+#[tokio::test]
+async fn times_out_and_cancels_calls() {
+    let (_server, connection, mut session) = common::connect(HubConnectionBuilder::new()).await;
+
+    let options = InvocationOptions {
+        timeout: Some(Duration::from_millis(50)),
+        ..Default::default()
+    };
+    let (result, invocation) = tokio::join!(connection.invoke_with("Slow", (), options), session.expect_invocation("Slow"));
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::Timeout);
+    // A late completion finds no pending invocation and is ignored.
+    session.complete(&invocation, json!(null));
+
+    let token = CancellationToken::new();
+    let options = InvocationOptions {
+        cancellation: Some(token.clone()),
+        ..Default::default()
+    };
+    let (result, ()) = tokio::join!(connection.invoke_with("Slow", (), options), async {
+        session.expect_invocation("Slow").await;
+        token.cancel();
+    });
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::Cancelled);
+
+    let (stream, invocation) =
+        tokio::join!(connection.stream("Counter", ()), session.expect_stream_invocation("Counter"));
+    let mut stream = stream.unwrap();
+    session.stream_item(&invocation, json!(0));
+    assert_eq!(stream.next().await.unwrap().unwrap(), json!(0));
+    drop(stream);
+    session.expect_cancel_invocation(&invocation).await;
+
+    let token = CancellationToken::new();
+    let options = InvocationOptions {
+        cancellation: Some(token.clone()),
+        ..Default::default()
+    };
+    let (stream, invocation) = tokio::join!(
+        connection.stream_with("Counter", (), options),
+        session.expect_stream_invocation("Counter")
+    );
+    let mut stream = stream.unwrap();
+    token.cancel();
+    assert_eq!(stream.next().await.unwrap().unwrap_err().kind(), ErrorKind::Cancelled);
+    assert!(stream.next().await.is_none());
+    session.expect_cancel_invocation(&invocation).await;
+
+    let (result, ()) = tokio::join!(connection.invoke("Add", (1, 2)), async {
+        let invocation = session.expect_invocation("Add").await;
+        session.complete(&invocation, json!(3));
+    });
+    assert_eq!(result.unwrap(), json!(3));
+}
+
+#[tokio::test]
+async fn a_stream_that_failed_to_start_is_not_cancelled() {
+    let builder = HubConnectionBuilder::new().with_outbound_queue(1, OutboundPolicy::FailFast);
+    let (_server, connection, mut session) = common::connect(builder).await;
+
+    // The second stream finds the queue full, the server never hears of it.
+    let (first, second) = tokio::join!(connection.stream("Counter", ()), connection.stream("Counter", ()));
+    let mut first = first.unwrap();
+    assert_eq!(second.err().unwrap().kind(), ErrorKind::QueueFull);
+    let invocation = session.expect_stream_invocation("Counter").await;
+    session.complete(&invocation, json!(null));
+    assert!(first.next().await.is_none());
+
+    let (result, ()) = tokio::join!(connection.invoke("Add", (1, 2)), async {
+        let invocation = session.expect_invocation("Add").await;
+        session.complete(&invocation, json!(3));
+    });
+    assert_eq!(result.unwrap(), json!(3));
+}
@@ -0,0 +1,17 @@
+//! Setup shared by the tests running against the mock hub.
+
+use signalr_rs::{
+    testing::{MockHubServer, MockSession},
+    HubConnection, HubConnectionBuilder,
+};
+
+/// Starts a mock hub and connects to it with `builder`, which gets the hub url.
+///
+/// The server is returned to keep it listening, reconnects go through [`MockHubServer::accept`].
+pub async fn connect(builder: HubConnectionBuilder) -> (MockHubServer, HubConnection, MockSession) {
+    let mut server = MockHubServer::start().await.unwrap();
+    let connection = builder.with_url(server.url()).build().unwrap();
+    let (started, session) = tokio::join!(connection.start(), server.accept());
+    started.unwrap();
+    (server, connection, session)
+}
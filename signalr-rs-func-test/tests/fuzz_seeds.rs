@@ -0,0 +1,117 @@
+//! Captures the fuzz seeds of `fuzz/corpus` from a running hub, run on demand with
+//! `cargo test -p signalr-rs-func-test --test fuzz_seeds -- --ignored`.
+//!
+//! The hub at `SIGNALR_CAPTURE_URL` is used when it is set, an ASP.NET Core hub with a
+//! `SendMessage(user, message)` method broadcasting `ReceiveMessage` like the chat sample; the
+//! crate's own hub serves the same methods otherwise.
+
+use std::{fs, net::TcpListener, path::Path, time::Duration};
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::json;
+use signalr_rs::{
+    server::{Hub, HubBuilder},
+    Error,
+};
+use tokio::time;
+use tokio_tungstenite::tungstenite::Message;
+
+const RECORD_SEPARATOR: &str = "\u{1e}";
+
+fn chat_hub() -> Hub {
+    HubBuilder::new()
+        .with_method("Add", |_, (a, b): (i32, i32)| async move { Ok(a + b) })
+        .with_method("SendMessage", |context, (user, message): (String, String)| async move {
+            context.clients().all().send("ReceiveMessage", (user, message)).await
+        })
+        .with_method("Fail", |_, ()| async move { Err::<(), _>(Error::hub_error("nope")) })
+        .build()
+}
+
+fn serve(hub: &Hub) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.set_nonblocking(true).unwrap();
+    let url = format!("http://{}/chat", listener.local_addr().unwrap());
+    let app = axum::Router::new().nest("/chat", hub.router());
+    tokio::spawn(axum::Server::from_tcp(listener).unwrap().serve(app.into_make_service()));
+    url
+}
+
+fn write_seed(target: &str, name: &str, contents: &str) {
+    let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("../fuzz/corpus").join(target);
+    fs::write(directory.join(format!("seed-captured-{}", name)), contents).unwrap();
+}
+
+async fn negotiate(url: &str, query: &str) -> String {
+    let body = reqwest::Client::new()
+        .post(format!("{}/negotiate{}", url, query))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    serde_json::from_str::<serde_json::Value>(&body).expect("the negotiate body is not JSON");
+    body
+}
+
+type Socket = tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+
+/// Negotiates and opens a socket, then sends the handshake for `protocol`.
+async fn open(url: &str, protocol: &str) -> Socket {
+    let negotiation: serde_json::Value = serde_json::from_str(&negotiate(url, "?negotiateVersion=1").await).unwrap();
+    let token = negotiation["connectionToken"].as_str().unwrap();
+    let socket_url = format!("{}?id={}", url.replacen("http", "ws", 1), token);
+    let (mut socket, _) = tokio_tungstenite::connect_async(socket_url).await.unwrap();
+    let handshake = json!({ "protocol": protocol, "version": 1 });
+    socket.send(Message::Text(format!("{}{}", handshake, RECORD_SEPARATOR))).await.unwrap();
+    socket
+}
+
+/// Text frames received until the server stays quiet for a while or closes the socket.
+async fn frames(socket: &mut Socket) -> Vec<String> {
+    let mut frames = Vec::new();
+    while let Ok(Some(Ok(message))) = time::timeout(Duration::from_secs(1), socket.next()).await {
+        match message {
+            Message::Text(text) => frames.push(text),
+            Message::Close(_) => break,
+            _ => {}
+        }
+    }
+    frames
+}
+
+#[tokio::test]
+#[ignore = "rewrites the fuzz seeds"]
+async fn captures_fuzz_seeds() {
+    let hub = chat_hub();
+    let url = std::env::var("SIGNALR_CAPTURE_URL").unwrap_or_else(|_| serve(&hub));
+
+    write_seed("negotiate", "v0", &negotiate(&url, "").await);
+    write_seed("negotiate", "v1", &negotiate(&url, "?negotiateVersion=1").await);
+
+    let mut rejected = open(&url, "messagepack").await;
+    write_seed("handshake", "rejected", &frames(&mut rejected).await.concat());
+
+    let mut socket = open(&url, "json").await;
+    let handshake = frames(&mut socket).await;
+    write_seed("handshake", "accepted", handshake.first().expect("no handshake response"));
+
+    let requests = [
+        json!({ "type": 1, "invocationId": "0", "target": "Add", "arguments": [1, 2] }),
+        json!({ "type": 1, "invocationId": "1", "target": "SendMessage", "arguments": ["rust", "Hello"] }),
+        json!({ "type": 1, "invocationId": "2", "target": "Fail", "arguments": [] }),
+        json!({ "type": 1, "invocationId": "3", "target": "Missing", "arguments": [null, { "nested": [1] }] }),
+        json!({ "type": 6 }),
+        json!({ "type": 7 }),
+    ];
+    let mut received = handshake[1..].to_vec();
+    for request in requests {
+        socket.send(Message::Text(format!("{}{}", request, RECORD_SEPARATOR))).await.unwrap();
+        received.extend(frames(&mut socket).await);
+    }
+    assert!(!received.is_empty(), "the hub sent no frames");
+    for (index, frame) in received.iter().enumerate() {
+        write_seed("records", &format!("frame-{}", index), frame);
+    }
+}
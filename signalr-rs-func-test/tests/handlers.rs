@@ -0,0 +1,115 @@
+mod common;
+
+use std::{sync::Arc, time::Duration};
+
+use futures_util::StreamExt;
+use serde_json::json;
+use signalr_rs::{
+    protocol::responses::{InvocationFields, Messsage},
+    DispatchMode, Headers, HubCallContext, HubConnectionBuilder,
+};
+use tokio::{sync::Semaphore, time};
+
+#[tokio::test]
+async fn calls_every_subscribed_handler() {
+    let (_server, connection, session) = common::connect(HubConnectionBuilder::new()).await;
+    let (sender, mut received) = tokio::sync::mpsc::unbounded_channel();
+    let subscribe = |name: &'static str| {
+        let sender = sender.clone();
+        connection.on("notify", move |(n,): (u32,)| {
+            let _ = sender.send((name, n));
+        })
+    };
+    let first = subscribe("first");
+    let second = subscribe("second");
+
+    // Targets match regardless of case, each handler sees every call.
+    session.invoke("Notify", (1,));
+    assert_eq!(received.recv().await, Some(("first", 1)));
+    assert_eq!(received.recv().await, Some(("second", 1)));
+
+    drop(first);
+    session.invoke("NOTIFY", (2,));
+    assert_eq!(received.recv().await, Some(("second", 2)));
+
+    second.detach();
+    connection.off("Notify");
+    subscribe("third").detach();
+    session.invoke("Notify", (3,));
+    assert_eq!(received.recv().await, Some(("third", 3)));
+    assert!(received.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn reports_unhandled_invocations_and_every_message() {
+    let builder = HubConnectionBuilder::new().with_unhandled_warnings(false);
+    let (_server, connection, mut session) = common::connect(builder).await;
+    let (sender, mut unhandled) = tokio::sync::mpsc::unbounded_channel();
+    connection.on_unhandled(move |target, arguments| {
+        let _ = sender.send((target.to_owned(), arguments));
+    });
+    let mut messages = connection.messages();
+
+    session.invoke("Missing", ("raw", 1));
+    assert_eq!(unhandled.recv().await, Some(("Missing".to_owned(), vec![json!("raw"), json!(1)])));
+    let (result, ()) = tokio::join!(connection.invoke("Add", (1, 2)), async {
+        let invocation = session.expect_invocation("Add").await;
+        session.complete(&invocation, json!(3));
+    });
+    result.unwrap();
+
+    let kinds: Vec<_> = messages.by_ref().take(2).map(|message| message.kind()).collect().await;
+    assert_eq!(kinds, vec!["Invocation", "Completion"]);
+}
+
+#[tokio::test]
+async fn replies_through_the_call_context() {
+    let (_server, connection, mut session) = common::connect(HubConnectionBuilder::new()).await;
+    connection.on_with_context("Greet", |context: HubCallContext, (name,): (String,)| async move {
+        assert_eq!(context.target(), "Greet");
+        assert_eq!(context.headers().get("tenant").map(String::as_str), Some("contoso"));
+        let reply = (format!("hello {}", name), context.connection_id().map(str::to_owned));
+        context.connection().send("Reply", reply).await.unwrap();
+    }).detach();
+
+    let headers = Headers::from([("tenant".to_owned(), "contoso".to_owned())]);
+    let greet = InvocationFields::new(None, "Greet", vec![json!("rust")]).with_headers(headers);
+    session.send(Messsage::Invocation(greet));
+    let reply = session.expect_invocation("Reply").await;
+    assert_eq!(reply.arguments, vec![json!("hello rust"), json!(session.connection_id())]);
+
+    let (stopped, ()) = tokio::join!(connection.stop(), session.expect_close());
+    stopped.unwrap();
+}
+
+#[tokio::test]
+async fn async_handlers_do_not_hold_up_completions() {
+    let builder = HubConnectionBuilder::new().with_dispatch_mode(DispatchMode::Parallel(2));
+    let (_server, connection, mut session) = common::connect(builder).await;
+    let started = Arc::new(Semaphore::new(0));
+    let release = Arc::new(Semaphore::new(0));
+    {
+        let (started, release) = (started.clone(), release.clone());
+        connection.on_async("Slow", move |(): ()| {
+            let (started, release) = (started.clone(), release.clone());
+            async move {
+                started.add_permits(1);
+                let _ = release.acquire().await;
+            }
+        }).detach();
+    }
+
+    session.invoke("Slow", ());
+    session.invoke("Slow", ());
+    // Both handlers run at once and the invocation completes while they wait.
+    time::timeout(Duration::from_secs(5), started.acquire_many(2)).await.unwrap().unwrap().forget();
+    let (result, ()) = tokio::join!(connection.invoke("Add", (1, 2)), async {
+        let invocation = session.expect_invocation("Add").await;
+        session.complete(&invocation, json!(3));
+    });
+    assert_eq!(result.unwrap(), json!(3));
+    release.add_permits(2);
+
+    let (stopped, ()) = tokio::join!(connection.stop(), session.expect_close());
+    stopped.unwrap();
+}
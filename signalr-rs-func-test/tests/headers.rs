@@ -0,0 +1,40 @@
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use serde_json::json;
+use signalr_rs::{
+    protocol::responses::{InvocationFields, Messsage},
+    Headers, HubConnectionBuilder, InvocationOptions,
+};
+
+#[tokio::test]
+async fn carries_headers_both_ways() {
+    let (_server, connection, mut session) = common::connect(HubConnectionBuilder::new()).await;
+    let received = Arc::new(Mutex::new(None));
+    {
+        let received = received.clone();
+        connection.on_with_headers("Notify", move |headers: &Headers, (): ()| {
+            *received.lock().unwrap() = headers.get("tenant").cloned();
+        }).detach();
+    }
+
+    let options = InvocationOptions {
+        headers: Headers::from([("tenant".to_owned(), "contoso".to_owned())]),
+        ..Default::default()
+    };
+    let (result, ()) = tokio::join!(connection.invoke_with("Ping", (), options), async {
+        let invocation = session.expect_invocation("Ping").await;
+        assert_eq!(invocation.headers.get("tenant").map(String::as_str), Some("contoso"));
+        let notify = InvocationFields::new(None, "Notify", vec![]).with_headers(invocation.headers.clone());
+        session.send(Messsage::Invocation(notify));
+        session.complete(&invocation, json!(null));
+    });
+    result.unwrap();
+
+    let (sent, ()) = tokio::join!(connection.send("Ack", ()), async {
+        session.expect_invocation("Ack").await;
+    });
+    sent.unwrap();
+    assert_eq!(received.lock().unwrap().as_deref(), Some("contoso"));
+}
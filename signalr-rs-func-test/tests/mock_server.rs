@@ -0,0 +1,102 @@
+mod common;
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use futures_util::StreamExt;
+use serde_json::json;
+use signalr_rs::{error::ErrorKind, HubConnection, HubConnectionBuilder, HubConnectionState};
+use tokio::time;
+
+async fn wait_for(connection: &HubConnection, state: HubConnectionState) {
+    let mut changes = connection.state_changes();
+    time::timeout(Duration::from_secs(5), changes.wait_for(|current| *current == state))
+        .await
+        .expect("state not reached in time")
+        .unwrap();
+}
+
+#[tokio::test]
+async fn invokes_and_receives_over_the_mock_hub() {
+    let (_server, connection, mut session) = common::connect(HubConnectionBuilder::new()).await;
+    assert_eq!(connection.connection_id().as_deref(), Some(session.connection_id()));
+    let received = Arc::new(Mutex::new(Vec::new()));
+    {
+        let received = received.clone();
+        connection.on("ReceiveMessage", move |(user, message): (String, String)| {
+            received.lock().unwrap().push(format!("{}: {}", user, message));
+        }).detach();
+    }
+
+    let (result, ()) = tokio::join!(connection.invoke("Add", (1, 2)), async {
+        let invocation = session.expect_invocation("Add").await;
+        assert_eq!(invocation.arguments, vec![json!(1), json!(2)]);
+        session.complete(&invocation, json!(3));
+    });
+    assert_eq!(result.unwrap(), json!(3));
+
+    let (result, ()) = tokio::join!(connection.invoke("Fail", ()), async {
+        let invocation = session.expect_invocation("Fail").await;
+        session.complete_with_error(&invocation, "boom");
+    });
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::Hub);
+
+    session.invoke("ReceiveMessage", ("server", "hello"));
+    let (sent, ()) = tokio::join!(connection.send("Ack", ()), async {
+        session.expect_invocation("Ack").await;
+    });
+    sent.unwrap();
+    assert_eq!(*received.lock().unwrap(), vec!["server: hello".to_owned()]);
+
+    let (stopped, ()) = tokio::join!(connection.stop(), session.expect_close());
+    stopped.unwrap();
+    assert_eq!(connection.state(), HubConnectionState::Disconnected);
+}
+
+#[tokio::test]
+async fn streams_items_from_the_mock_hub() {
+    let (_server, connection, mut session) = common::connect(HubConnectionBuilder::new()).await;
+
+    let (items, ()) = tokio::join!(
+        async { connection.stream("Counter", (3,)).await.unwrap().collect::<Vec<_>>().await },
+        async {
+            let invocation = session.expect_stream_invocation("Counter").await;
+            for i in 0..3 {
+                session.stream_item(&invocation, json!(i));
+            }
+            session.complete(&invocation, json!(null));
+        }
+    );
+    let items: Vec<_> = items.into_iter().map(Result::unwrap).collect();
+    assert_eq!(items, vec![json!(0), json!(1), json!(2)]);
+}
+
+#[tokio::test]
+async fn stops_with_a_normal_close_frame() {
+    let (_server, connection, mut session) = common::connect(HubConnectionBuilder::new()).await;
+
+    let (stopped, ()) = tokio::join!(connection.stop(), session.expect_close());
+    stopped.unwrap();
+    assert_eq!(session.expect_close_frame().await, Some(1000));
+}
+
+#[tokio::test]
+async fn reconnects_after_the_connection_drops() {
+    let builder = HubConnectionBuilder::new().with_automatic_reconnect(vec![Duration::from_millis(0)]);
+    let (mut server, connection, session) = common::connect(builder).await;
+
+    session.drop_connection();
+    let mut session = server.accept().await;
+    wait_for(&connection, HubConnectionState::Connected).await;
+
+    let (result, ()) = tokio::join!(connection.invoke("Echo", ("again",)), async {
+        let invocation = session.expect_invocation("Echo").await;
+        session.complete(&invocation, invocation.arguments[0].clone());
+    });
+    assert_eq!(result.unwrap(), json!("again"));
+
+    session.close(Some("server shutting down"), false);
+    wait_for(&connection, HubConnectionState::Disconnected).await;
+}
@@ -0,0 +1,68 @@
+mod common;
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use serde_json::json;
+use signalr_rs::{ConnectionObserver, HubConnectionBuilder};
+
+#[derive(Default)]
+struct Recorded {
+    sent: Vec<&'static str>,
+    received: Vec<&'static str>,
+    bytes_sent: usize,
+    completed: Vec<(String, bool)>,
+    pending: Vec<usize>,
+}
+
+#[derive(Clone, Default)]
+struct Recorder(Arc<Mutex<Recorded>>);
+
+impl ConnectionObserver for Recorder {
+    fn message_sent(&self, kind: &'static str, bytes: usize) {
+        let mut recorded = self.0.lock().unwrap();
+        recorded.sent.push(kind);
+        recorded.bytes_sent += bytes;
+    }
+
+    fn message_received(&self, kind: &'static str, _bytes: usize) {
+        self.0.lock().unwrap().received.push(kind);
+    }
+
+    fn invocation_completed(&self, target: &str, _latency: Duration, succeeded: bool) {
+        self.0.lock().unwrap().completed.push((target.to_owned(), succeeded));
+    }
+
+    fn pending_invocations(&self, count: usize) {
+        self.0.lock().unwrap().pending.push(count);
+    }
+}
+
+#[tokio::test]
+async fn reports_messages_and_invocations_to_the_observer() {
+    let recorder = Recorder::default();
+    let builder = HubConnectionBuilder::new().with_observer(recorder.clone());
+    let (_server, connection, mut session) = common::connect(builder).await;
+
+    let (result, ()) = tokio::join!(connection.invoke("Add", (1, 2)), async {
+        let invocation = session.expect_invocation("Add").await;
+        session.complete(&invocation, json!(3));
+    });
+    result.unwrap();
+    let (result, ()) = tokio::join!(connection.invoke("Fail", ()), async {
+        let invocation = session.expect_invocation("Fail").await;
+        session.complete_with_error(&invocation, "boom");
+    });
+    result.unwrap_err();
+    let (stopped, ()) = tokio::join!(connection.stop(), session.expect_close());
+    stopped.unwrap();
+
+    let recorded = recorder.0.lock().unwrap();
+    assert_eq!(recorded.sent, vec!["Invocation", "Invocation", "Close"]);
+    assert!(recorded.bytes_sent > 0);
+    assert_eq!(recorded.received, vec!["Completion", "Completion"]);
+    assert_eq!(recorded.completed, vec![("Add".to_owned(), true), ("Fail".to_owned(), false)]);
+    assert_eq!(recorded.pending, vec![1, 0, 1, 0]);
+}
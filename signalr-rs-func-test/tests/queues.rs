@@ -0,0 +1,105 @@
+mod common;
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use futures_util::StreamExt;
+use serde_json::json;
+use signalr_rs::{error::ErrorKind, HubConnectionBuilder, OutboundPolicy};
+use tokio::{sync::Semaphore, time};
+
+#[tokio::test]
+async fn bounds_the_outbound_and_inbound_queues() {
+    let builder = HubConnectionBuilder::new()
+        .with_outbound_queue(1, OutboundPolicy::FailFast)
+        .with_inbound_capacity(1);
+    let (_server, connection, mut session) = common::connect(builder).await;
+    let received = Arc::new(Mutex::new(Vec::new()));
+    {
+        let received = received.clone();
+        connection.on("Count", move |(n,): (u32,)| received.lock().unwrap().push(n)).detach();
+    }
+
+    // Both sends are queued before the writer runs, the second finds the queue full.
+    let (first, second) = tokio::join!(connection.send("First", ()), connection.send("Second", ()));
+    first.unwrap();
+    assert_eq!(second.unwrap_err().kind(), ErrorKind::QueueFull);
+    session.expect_invocation("First").await;
+
+    for n in 0..20 {
+        session.invoke("Count", (n,));
+    }
+    let (result, ()) = tokio::join!(connection.invoke("Done", ()), async {
+        let invocation = session.expect_invocation("Done").await;
+        session.complete(&invocation, json!(null));
+    });
+    result.unwrap();
+    assert_eq!(*received.lock().unwrap(), (0..20).collect::<Vec<_>>());
+
+    let (stopped, ()) = tokio::join!(connection.stop(), session.expect_close());
+    stopped.unwrap();
+}
+
+#[tokio::test]
+async fn a_saturated_handler_backlog_holds_up_results() {
+    let builder = HubConnectionBuilder::new().with_inbound_capacity(1);
+    let (_server, connection, mut session) = common::connect(builder).await;
+    let release = Arc::new(Semaphore::new(0));
+    {
+        let release = release.clone();
+        connection.on_async("Slow", move |(): ()| {
+            let release = release.clone();
+            async move {
+                let _ = release.acquire().await.unwrap();
+            }
+        }).detach();
+    }
+
+    // One call runs, one waits for the backlog and one for the dispatcher, the reader stops there.
+    for _ in 0..3 {
+        session.invoke("Slow", ());
+    }
+    let invoke = connection.invoke("Add", (1, 2));
+    tokio::pin!(invoke);
+    let (pending, ()) = tokio::join!(time::timeout(Duration::from_millis(200), &mut invoke), async {
+        let invocation = session.expect_invocation("Add").await;
+        session.complete(&invocation, json!(3));
+    });
+    assert!(pending.is_err());
+
+    release.add_permits(3);
+    assert_eq!(invoke.await.unwrap(), json!(3));
+}
+
+#[tokio::test]
+async fn unread_stream_items_hold_up_the_connection() {
+    let builder = HubConnectionBuilder::new().with_inbound_capacity(1);
+    let (_server, connection, mut session) = common::connect(builder).await;
+
+    let (stream, invocation) =
+        tokio::join!(connection.stream("Counter", ()), session.expect_stream_invocation("Counter"));
+    let stream = stream.unwrap();
+    for i in 0..5 {
+        session.stream_item(&invocation, json!(i));
+    }
+    session.complete_with_error(&invocation, "boom");
+
+    let invoke = connection.invoke("Add", (1, 2));
+    tokio::pin!(invoke);
+    let (pending, ()) = tokio::join!(time::timeout(Duration::from_millis(200), &mut invoke), async {
+        let invocation = session.expect_invocation("Add").await;
+        session.complete(&invocation, json!(3));
+    });
+    assert!(pending.is_err());
+
+    // Every item and then the error arrive once the stream is read.
+    let items: Vec<_> = stream.collect().await;
+    assert_eq!(items.len(), 6);
+    for (i, item) in items[..5].iter().enumerate() {
+        assert_eq!(item.as_ref().unwrap(), &json!(i));
+    }
+    assert_eq!(items[5].as_ref().unwrap_err().kind(), ErrorKind::Hub);
+    assert_eq!(invoke.await.unwrap(), json!(3));
+}
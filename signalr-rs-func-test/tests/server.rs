@@ -0,0 +1,171 @@
+use std::{net::TcpListener, time::Duration};
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use signalr_rs::{
+    error::ErrorKind,
+    server::{BackplaneHubLifetimeManager, Hub, HubBuilder, InMemoryBackplane},
+    Error, HubConnection, HubConnectionBuilder,
+};
+use tokio::{sync::mpsc, time};
+use tokio_tungstenite::tungstenite::{self, Message};
+
+fn chat_hub() -> Hub {
+    HubBuilder::new()
+        .with_method("Add", |_, (a, b): (i32, i32)| async move { Ok(a + b) })
+        .with_method("Broadcast", |context, (message,): (String,)| async move {
+            context.clients().others().send("Receive", (format!("others: {}", message),)).await?;
+            context.clients().caller().send("Receive", (format!("caller: {}", message),)).await
+        })
+        .with_method("Join", |context, (group,): (String,)| async move {
+            context.groups().add_to_group(context.connection_id(), &group).await
+        })
+        .with_method("SendToGroup", |context, (group, message): (String, String)| async move {
+            context.clients().group(&group).send("Receive", (message,)).await
+        })
+        .with_method("SendToUser", |context, (message,): (String,)| async move {
+            let user = context.user_id().unwrap_or_default().to_owned();
+            context.clients().user(&user).send("Receive", (message,)).await
+        })
+        .with_user_id_provider(|_, uri| Some(uri.path().to_owned()))
+        .with_method("Fail", |_, ()| async move { Err::<(), _>(Error::hub_error("nope")) })
+        .build()
+}
+
+/// Serves `hub` at `/chat` on an ephemeral port, returning the hub url.
+fn serve(hub: &Hub) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    listener.set_nonblocking(true).unwrap();
+    let url = format!("http://{}/chat", listener.local_addr().unwrap());
+    let app = axum::Router::new().nest("/chat", hub.router());
+    tokio::spawn(axum::Server::from_tcp(listener).unwrap().serve(app.into_make_service()));
+    url
+}
+
+async fn connect(url: &str) -> (HubConnection, mpsc::UnboundedReceiver<String>) {
+    let connection = HubConnectionBuilder::new().with_url(url.to_owned()).build().unwrap();
+    let (sender, receiver) = mpsc::unbounded_channel();
+    connection.on("Receive", move |(message,): (String,)| {
+        let _ = sender.send(message);
+    }).detach();
+    connection.start().await.unwrap();
+    (connection, receiver)
+}
+
+async fn next(receiver: &mut mpsc::UnboundedReceiver<String>) -> String {
+    time::timeout(Duration::from_secs(5), receiver.recv()).await.unwrap().unwrap()
+}
+
+#[tokio::test]
+async fn serves_rust_clients() {
+    let hub = chat_hub();
+    let url = serve(&hub);
+    let (first, mut first_messages) = connect(&url).await;
+    let (second, mut second_messages) = connect(&url).await;
+
+    assert_eq!(first.invoke("add", (2, 3)).await.unwrap(), json!(5));
+    let error = first.invoke("Fail", ()).await.unwrap_err();
+    assert_eq!((error.kind(), error.message()), (ErrorKind::Hub, "nope"));
+    let error = first.invoke("Missing", ()).await.unwrap_err();
+    assert_eq!(error.message(), "Unknown hub method 'Missing'");
+    let error = first.invoke("Add", ("two", 3)).await.unwrap_err();
+    assert_eq!(error.message(), "An unexpected error occurred invoking 'Add' on the server.");
+
+    first.invoke("Broadcast", ("hi",)).await.unwrap();
+    assert_eq!(next(&mut first_messages).await, "caller: hi");
+    assert_eq!(next(&mut second_messages).await, "others: hi");
+
+    hub.clients().all().send("Receive", ("everyone",)).await.unwrap();
+    assert_eq!(next(&mut first_messages).await, "everyone");
+    assert_eq!(next(&mut second_messages).await, "everyone");
+
+    second.invoke("Join", ("room",)).await.unwrap();
+    first.invoke("SendToGroup", ("room", "to the room")).await.unwrap();
+    assert_eq!(next(&mut second_messages).await, "to the room");
+    first.invoke("SendToUser", ("to the user",)).await.unwrap();
+    assert_eq!(next(&mut first_messages).await, "to the user");
+    assert_eq!(next(&mut second_messages).await, "to the user");
+    assert!(first_messages.try_recv().is_err());
+
+    first.stop().await.unwrap();
+    second.stop().await.unwrap();
+}
+
+#[tokio::test]
+async fn shares_groups_between_nodes_through_the_backplane() {
+    let backplane = InMemoryBackplane::new();
+    let mut nodes = Vec::new();
+    for _ in 0..2 {
+        let manager = BackplaneHubLifetimeManager::new(backplane.clone()).await.unwrap();
+        let hub = HubBuilder::new()
+            .with_method("Join", |context, (connection_id, group): (String, String)| async move {
+                context.groups().add_to_group(&connection_id, &group).await
+            })
+            .with_method("SendToGroup", |context, (group, message): (String, String)| async move {
+                context.clients().group(&group).send("Receive", (message,)).await
+            })
+            .with_lifetime_manager(manager)
+            .build();
+        nodes.push(serve(&hub));
+    }
+    let (first, _first_messages) = connect(&nodes[0]).await;
+    let (second, mut second_messages) = connect(&nodes[1]).await;
+
+    // The first node adds a connection it does not own, the second one applies it.
+    let member = second.connection_id().unwrap();
+    first.invoke("Join", (member, "room")).await.unwrap();
+    first.invoke("SendToGroup", ("room", "across nodes")).await.unwrap();
+    assert_eq!(next(&mut second_messages).await, "across nodes");
+
+    first.stop().await.unwrap();
+    second.stop().await.unwrap();
+}
+
+/// Status the hub answered a WebSocket request with, when it refused the upgrade.
+async fn refused(url: String) -> u16 {
+    match tokio_tungstenite::connect_async(url).await {
+        Err(tungstenite::Error::Http(response)) => response.status().as_u16(),
+        Err(error) => panic!("unexpected error {}", error),
+        Ok(_) => panic!("the upgrade was accepted"),
+    }
+}
+
+#[tokio::test]
+async fn refuses_reused_and_unknown_connection_tokens() {
+    let hub = chat_hub();
+    let url = serve(&hub);
+    let body = reqwest::Client::new()
+        .post(format!("{}/negotiate?negotiateVersion=1", url))
+        .send()
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    let negotiation: Value = serde_json::from_str(&body).unwrap();
+    let connection_id = negotiation["connectionId"].as_str().unwrap();
+    let token = negotiation["connectionToken"].as_str().unwrap();
+    assert_ne!(connection_id, token);
+
+    // The token is percent-decoded before it is looked up.
+    let socket_url = url.replacen("http://", "ws://", 1);
+    let encoded: String = token.bytes().map(|byte| format!("%{:02X}", byte)).collect();
+    let (mut socket, _) = tokio_tungstenite::connect_async(format!("{}?id={}", socket_url, encoded)).await.unwrap();
+    socket
+        .send(Message::Text("{\"protocol\":\"json\",\"version\":1}\u{1e}".to_owned()))
+        .await
+        .unwrap();
+    assert_eq!(socket.next().await.unwrap().unwrap(), Message::Text("{}\u{1e}".to_owned()));
+
+    // A second socket cannot take over the live connection, nor connect by its public id.
+    assert_eq!(refused(format!("{}?id={}", socket_url, token)).await, 409);
+    assert_eq!(refused(format!("{}?id={}", socket_url, connection_id)).await, 404);
+
+    let invocation = json!({ "type": 1, "invocationId": "1", "target": "Add", "arguments": [1, 2] });
+    socket.send(Message::Text(format!("{}\u{1e}", invocation))).await.unwrap();
+    let completion = match time::timeout(Duration::from_secs(5), socket.next()).await.unwrap().unwrap().unwrap() {
+        Message::Text(text) => serde_json::from_str::<Value>(text.trim_end_matches('\u{1e}')).unwrap(),
+        message => panic!("unexpected message {:?}", message),
+    };
+    assert_eq!(completion["result"], json!(3));
+}
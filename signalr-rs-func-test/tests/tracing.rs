@@ -0,0 +1,46 @@
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use serde_json::json;
+use signalr_rs::{
+    protocol::responses::{InvocationFields, Messsage},
+    trace_context::TRACEPARENT,
+    Headers, HubConnectionBuilder, InvocationOptions, TraceContext,
+};
+
+#[tokio::test]
+async fn propagates_trace_context_through_invocations() {
+    let (_server, connection, mut session) = common::connect(HubConnectionBuilder::new()).await;
+    let handled = Arc::new(Mutex::new(None));
+    {
+        let handled = handled.clone();
+        connection.on("Notify", move |(): ()| {
+            *handled.lock().unwrap() = TraceContext::current();
+        }).detach();
+    }
+
+    let (sent, ()) = tokio::join!(connection.send("Ping", ()), async {
+        let invocation = session.expect_invocation("Ping").await;
+        assert!(TraceContext::extract(&invocation.headers).is_some());
+    });
+    sent.unwrap();
+
+    let parent = TraceContext::new_root();
+    let mut headers = Headers::new();
+    parent.inject(&mut headers);
+    session.send(Messsage::Invocation(InvocationFields::new(None, "Notify", vec![]).with_headers(headers.clone())));
+
+    let (result, ()) = tokio::join!(connection.invoke_with("Echo", (), InvocationOptions { headers, ..Default::default() }), async {
+        let invocation = session.expect_invocation("Echo").await;
+        let context = TraceContext::extract(&invocation.headers).unwrap();
+        assert_eq!(context.trace_id(), parent.trace_id());
+        assert_ne!(invocation.headers[TRACEPARENT], parent.traceparent());
+        session.complete(&invocation, json!(null));
+    });
+    result.unwrap();
+
+    let handled = handled.lock().unwrap().clone().expect("the handler ran without a trace context");
+    assert_eq!(handled.trace_id(), parent.trace_id());
+    assert_ne!(handled.span_id(), parent.span_id());
+}
@@ -0,0 +1,118 @@
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned};
+use syn::{
+    spanned::Spanned, GenericArgument, ItemTrait, PathArguments, Result, ReturnType, TraitItem, TraitItemFn, Type,
+    TypeParamBound,
+};
+
+use crate::method::{arguments_expr, hub_method_name, parameters, plain_inputs, take_hub_attributes};
+
+pub fn expand(mut item: ItemTrait) -> Result<TokenStream> {
+    let mut methods = Vec::new();
+    for trait_item in item.items.iter_mut() {
+        if let TraitItem::Fn(method) = trait_item {
+            let attributes = take_hub_attributes(&mut method.attrs)?;
+            if method.default.is_some() {
+                continue;
+            }
+            methods.push(expand_method(method, &attributes)?);
+        }
+    }
+
+    let ident = &item.ident;
+    let (impl_generics, ty_generics, where_clause) = item.generics.split_for_impl();
+    Ok(quote! {
+        #item
+
+        // `Ok(..?)` keeps the error conversion working for any `E: From<signalr_rs::Error>`.
+        #[allow(clippy::needless_question_mark)]
+        impl #impl_generics #ident #ty_generics for ::signalr_rs::HubConnection #where_clause {
+            #(#methods)*
+        }
+    })
+}
+
+/// Rewrites the trait method if needed and returns its implementation for `HubConnection`.
+fn expand_method(method: &mut TraitItemFn, attributes: &crate::method::HubAttributes) -> Result<TokenStream> {
+    let name = hub_method_name(&method.sig.ident, attributes);
+    let parameters = parameters(&method.sig)?;
+    let arguments = arguments_expr(&parameters);
+    let span = method.sig.span();
+
+    if method.sig.asyncness.is_some() {
+        let output = match &method.sig.output {
+            ReturnType::Type(_, ty) => (**ty).clone(),
+            ReturnType::Default => {
+                return Err(syn::Error::new(span, "async hub methods must return a `Result`"));
+            }
+        };
+        // Spell the future out so callers can rely on it being `Send`.
+        method.sig.asyncness = None;
+        method.sig.output = syn::parse_quote! {
+            -> impl ::core::future::Future<Output = #output> + ::core::marker::Send
+        };
+
+        let call = if attributes.send {
+            quote! {
+                self.send(#name, __arguments?).await?;
+                ::core::result::Result::Ok(())
+            }
+        } else {
+            quote! {
+                let __result = self.invoke(#name, __arguments?).await?;
+                ::core::result::Result::Ok(::signalr_rs::macro_support::from_result(__result)?)
+            }
+        };
+        let mut sig = method.sig.clone();
+        sig.inputs = plain_inputs(&method.sig, &parameters);
+        return Ok(quote_spanned! {span=>
+            #sig {
+                let __arguments = #arguments;
+                async move { #call }
+            }
+        });
+    }
+
+    if attributes.send {
+        return Err(syn::Error::new(span, "`#[hub(send)]` only applies to async methods"));
+    }
+    let item = stream_item(&method.sig.output)
+        .ok_or_else(|| syn::Error::new(span, "hub methods must be `async fn` or return `impl Stream<Item = T>`"))?;
+    let forward = if is_result(item) {
+        quote!(::signalr_rs::macro_support::stream_results(self, #name, #arguments))
+    } else {
+        quote!(::signalr_rs::macro_support::stream_items(self, #name, #arguments))
+    };
+    let mut sig = method.sig.clone();
+    sig.inputs = plain_inputs(&method.sig, &parameters);
+    Ok(quote_spanned! {span=>
+        #sig {
+            #forward
+        }
+    })
+}
+
+/// The `Item` of an `impl Stream<Item = ...>` return type.
+fn stream_item(output: &ReturnType) -> Option<&Type> {
+    let ReturnType::Type(_, ty) = output else { return None };
+    let Type::ImplTrait(impl_trait) = &**ty else { return None };
+    impl_trait.bounds.iter().find_map(|bound| {
+        let TypeParamBound::Trait(bound) = bound else { return None };
+        let segment = bound.path.segments.last()?;
+        if segment.ident != "Stream" {
+            return None;
+        }
+        let PathArguments::AngleBracketed(arguments) = &segment.arguments else { return None };
+        arguments.args.iter().find_map(|argument| match argument {
+            GenericArgument::AssocType(assoc) if assoc.ident == "Item" => Some(&assoc.ty),
+            _ => None,
+        })
+    })
+}
+
+fn is_result(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path.path.segments.last().is_some_and(|segment| segment.ident == "Result"),
+        _ => false,
+    }
+}
@@ -1,8 +1,53 @@
-#[cfg(test)]
-mod tests {
-    #[test]
-    fn it_works() {
-        let result = 2 + 2;
-        assert_eq!(result, 4);
+//! Procedural macros behind `signalr-rs`'s typed hub proxies, use them through the re-exports
+//! in `signalr_rs`.
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, Item, ItemTrait};
+
+mod client;
+mod method;
+mod receiver;
+
+/// Implements a trait of hub methods for `signalr_rs::HubConnection`.
+///
+/// Every method takes `&self` and is forwarded to the hub method with the PascalCase name of
+/// the Rust method:
+///
+/// * `async fn` returning a `Result` calls `HubConnection::invoke` and deserializes the result.
+///   With `#[hub(send)]` it calls `HubConnection::send` instead and must return `Result<()>`.
+/// * `fn` returning `impl Stream<Item = T>` calls `HubConnection::stream`. Items that fail to
+///   arrive end the stream, use `Item = Result<T>` to observe the error instead.
+///
+/// `#[hub(name = "Method")]` overrides the hub method name.
+#[proc_macro_attribute]
+pub fn hub_client(attr: TokenStream, item: TokenStream) -> TokenStream {
+    if !attr.is_empty() {
+        return syn::Error::new(proc_macro2::Span::call_site(), "hub_client takes no arguments")
+            .to_compile_error()
+            .into();
     }
+    let item = parse_macro_input!(item as ItemTrait);
+    client::expand(item)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Turns a trait of server-to-client methods into something `HubConnection::register` accepts.
+///
+/// Put it on the trait and on every `impl` of it, the trait methods must be
+/// `async fn name(&self, ...)` with owned, deserializable parameters and no return value.
+/// Method names map to hub methods like in [`macro@hub_client`], including `#[hub(name = "...")]`.
+#[proc_macro_attribute]
+pub fn hub_receiver(attr: TokenStream, item: TokenStream) -> TokenStream {
+    if !attr.is_empty() {
+        return syn::Error::new(proc_macro2::Span::call_site(), "hub_receiver takes no arguments")
+            .to_compile_error()
+            .into();
+    }
+    let result = match parse_macro_input!(item as Item) {
+        Item::Trait(item) => receiver::expand_trait(item),
+        Item::Impl(item) => receiver::expand_impl(item),
+        other => Err(syn::Error::new_spanned(other, "`#[hub_receiver]` expects a trait or an impl of a receiver trait")),
+    };
+    result.unwrap_or_else(syn::Error::into_compile_error).into()
 }
@@ -0,0 +1,129 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{
+    ext::IdentExt, punctuated::Punctuated, spanned::Spanned, Attribute, FnArg, Ident, LitStr, Pat, Result, Signature, Token, Type,
+};
+
+/// What a `#[hub(...)]` attribute asked for.
+#[derive(Default)]
+pub struct HubAttributes {
+    pub name: Option<String>,
+    pub send: bool,
+}
+
+/// Removes the `#[hub(...)]` attributes from `attrs` and parses them.
+pub fn take_hub_attributes(attrs: &mut Vec<Attribute>) -> Result<HubAttributes> {
+    let mut parsed = HubAttributes::default();
+    let mut error = None;
+    attrs.retain(|attr| {
+        if !attr.path().is_ident("hub") {
+            return true;
+        }
+        let result = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("send") {
+                parsed.send = true;
+                Ok(())
+            } else if meta.path.is_ident("name") {
+                parsed.name = Some(meta.value()?.parse::<LitStr>()?.value());
+                Ok(())
+            } else {
+                Err(meta.error("expected `send` or `name = \"...\"`"))
+            }
+        });
+        if let Err(e) = result {
+            error.get_or_insert(e);
+        }
+        false
+    });
+    match error {
+        Some(error) => Err(error),
+        None => Ok(parsed),
+    }
+}
+
+/// The hub method name for a Rust method, `send_message` becomes `SendMessage`.
+pub fn hub_method_name(ident: &Ident, attributes: &HubAttributes) -> String {
+    match &attributes.name {
+        Some(name) => name.clone(),
+        None => to_pascal_case(&ident.unraw().to_string()),
+    }
+}
+
+/// `signalr-rs-codegen` keeps a copy to know when to pin a name, the tests of both pin the same cases.
+pub fn to_pascal_case(name: &str) -> String {
+    name.split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+/// Largest tuple `IntoHubArgs` and `FromHubArgs` are implemented for.
+const MAX_PARAMETERS: usize = 16;
+
+/// The parameters of a hub method, `&self` excluded.
+pub struct Parameters {
+    pub names: Vec<Ident>,
+    pub types: Vec<Type>,
+}
+
+/// Checks the method takes `&self` and collects its other parameters.
+pub fn parameters(sig: &Signature) -> Result<Parameters> {
+    let mut inputs = sig.inputs.iter();
+    match inputs.next() {
+        Some(FnArg::Receiver(receiver)) if receiver.reference.is_some() && receiver.mutability.is_none() => {}
+        _ => return Err(syn::Error::new(sig.span(), "hub methods must take `&self`")),
+    }
+
+    let mut parameters = Parameters { names: Vec::new(), types: Vec::new() };
+    for input in inputs {
+        let FnArg::Typed(typed) = input else { unreachable!("only the first argument can be a receiver") };
+        match &*typed.pat {
+            Pat::Ident(pat) => parameters.names.push(pat.ident.clone()),
+            other => return Err(syn::Error::new(other.span(), "hub method parameters must be plain identifiers")),
+        }
+        parameters.types.push((*typed.ty).clone());
+    }
+    if parameters.names.len() > MAX_PARAMETERS {
+        return Err(syn::Error::new(sig.inputs.span(), "hub methods take at most 16 parameters"));
+    }
+    Ok(parameters)
+}
+
+/// The parameter list with patterns stripped down to their identifiers.
+pub fn plain_inputs(sig: &Signature, parameters: &Parameters) -> Punctuated<FnArg, Token![,]> {
+    let receiver = sig.inputs.first().cloned();
+    let names = &parameters.names;
+    let types = &parameters.types;
+    let mut inputs: Punctuated<FnArg, Token![,]> = receiver.into_iter().collect();
+    inputs.extend(names.iter().zip(types).map(|(name, ty)| -> FnArg { syn::parse_quote!(#name: #ty) }));
+    inputs
+}
+
+/// Expression serializing every parameter, evaluates to `Result<Vec<Value>, signalr_rs::Error>`.
+pub fn arguments_expr(parameters: &Parameters) -> TokenStream {
+    let names = &parameters.names;
+    quote! {
+        ::signalr_rs::IntoHubArgs::into_hub_args((#(&#names,)*))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::to_pascal_case;
+
+    #[test]
+    fn converts_snake_case_to_pascal_case() {
+        assert_eq!(to_pascal_case("send_message"), "SendMessage");
+        assert_eq!(to_pascal_case("stream_counter"), "StreamCounter");
+        assert_eq!(to_pascal_case("echo"), "Echo");
+        assert_eq!(to_pascal_case("__private_call"), "PrivateCall");
+        assert_eq!(to_pascal_case("get_http_status"), "GetHttpStatus");
+        assert_eq!(to_pascal_case("utf8_name"), "Utf8Name");
+    }
+}
@@ -0,0 +1,112 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote, quote_spanned};
+use syn::{ext::IdentExt, spanned::Spanned, Ident, ImplItem, ItemImpl, ItemTrait, Result, ReturnType, TraitItem, Type};
+
+use crate::method::{hub_method_name, parameters, take_hub_attributes};
+
+/// Hidden associated const holding the hub method name of a receiver method.
+fn target_const(method: &Ident) -> Ident {
+    format_ident!("__SIGNALR_{}", method.unraw().to_string().to_uppercase())
+}
+
+fn returns_unit(output: &ReturnType) -> bool {
+    match output {
+        ReturnType::Default => true,
+        ReturnType::Type(_, ty) => matches!(&**ty, Type::Tuple(tuple) if tuple.elems.is_empty()),
+    }
+}
+
+/// Makes every receiver future `Send` and records the hub method names on the trait.
+pub fn expand_trait(mut item: ItemTrait) -> Result<TokenStream> {
+    let mut targets = Vec::new();
+    for trait_item in item.items.iter_mut() {
+        let TraitItem::Fn(method) = trait_item else { continue };
+        let attributes = take_hub_attributes(&mut method.attrs)?;
+        let span = method.sig.span();
+        if attributes.send {
+            return Err(syn::Error::new(span, "`#[hub(send)]` does not apply to receivers"));
+        }
+        if method.sig.asyncness.is_none() || !returns_unit(&method.sig.output) {
+            return Err(syn::Error::new(span, "receiver methods must be `async fn` without a return value"));
+        }
+        parameters(&method.sig)?;
+
+        method.sig.asyncness = None;
+        method.sig.output = syn::parse_quote! {
+            -> impl ::core::future::Future<Output = ()> + ::core::marker::Send
+        };
+        let name = hub_method_name(&method.sig.ident, &attributes);
+        let constant = target_const(&method.sig.ident);
+        targets.push(quote! {
+            #[doc(hidden)]
+            const #constant: &'static str = #name;
+        });
+    }
+
+    item.items.extend(targets.into_iter().map(TraitItem::Verbatim));
+    Ok(quote!(#item))
+}
+
+/// Implements `signalr_rs::HubReceiver` for the type the receiver trait is implemented for.
+pub fn expand_impl(item: ItemImpl) -> Result<TokenStream> {
+    let trait_path = match &item.trait_ {
+        Some((None, path, _)) => path,
+        _ => return Err(syn::Error::new(item.span(), "`#[hub_receiver]` expects a trait or an impl of a receiver trait")),
+    };
+    let self_ty = &item.self_ty;
+
+    let mut handlers = Vec::new();
+    for impl_item in item.items.iter() {
+        let ImplItem::Fn(method) = impl_item else { continue };
+        let span = method.sig.span();
+        let parameters = parameters(&method.sig)?;
+        let ident = &method.sig.ident;
+        let constant = target_const(ident);
+        let names = &parameters.names;
+        let types = &parameters.types;
+        handlers.push(quote_spanned! {span=>
+            {
+                let receiver = ::std::sync::Arc::clone(&self);
+                ::signalr_rs::ReceiverHandler::new(
+                    <#self_ty as #trait_path>::#constant,
+                    move |arguments| {
+                        let target = <#self_ty as #trait_path>::#constant;
+                        let (#(#names,)*): (#(#types,)*) = ::signalr_rs::FromHubArgs::from_hub_args(target, arguments)?;
+                        let receiver = ::std::sync::Arc::clone(&receiver);
+                        ::core::result::Result::Ok(::std::boxed::Box::pin(async move {
+                            <#self_ty as #trait_path>::#ident(&receiver, #(#names),*).await
+                        }))
+                    },
+                )
+            }
+        });
+    }
+
+    let (impl_generics, _, where_clause) = item.generics.split_for_impl();
+    Ok(quote! {
+        #item
+
+        impl #impl_generics ::signalr_rs::HubReceiver for #self_ty #where_clause {
+            fn handlers(self: ::std::sync::Arc<Self>) -> ::std::vec::Vec<::signalr_rs::ReceiverHandler> {
+                ::std::vec![#(#handlers),*]
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::expand_trait;
+
+    #[test]
+    fn names_keyword_methods_without_the_raw_prefix() {
+        let expanded = expand_trait(syn::parse_quote! {
+            trait Receiver {
+                async fn r#type(&self, value: String);
+            }
+        })
+        .unwrap()
+        .to_string();
+        assert!(expanded.contains("const __SIGNALR_TYPE : & 'static str = \"Type\""), "{}", expanded);
+    }
+}
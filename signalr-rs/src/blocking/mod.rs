@@ -0,0 +1,437 @@
+//! A synchronous client for tools that do not want an async runtime.
+//!
+//! The connection is driven by a background thread on top of the synchronous `tungstenite`
+//! socket, negotiation goes through the blocking `reqwest` client.
+
+use std::{
+    collections::HashMap,
+    io::{self, ErrorKind as IoErrorKind},
+    net::TcpStream,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, TryRecvError},
+        Arc, Mutex, MutexGuard, Weak,
+    },
+    thread::{self, JoinHandle, ThreadId},
+    time::{Duration, Instant},
+};
+
+use serde_json::Value;
+use tungstenite::{stream::MaybeTlsStream, Message, WebSocket};
+
+use crate::{
+    connection::listener_key,
+    error::Error,
+    runtime,
+    protocol::{
+        self,
+        arguments::{FromHubArgs, IntoHubArgs},
+        responses::{CloseFields, CompletionFields, InvocationFields, Messsage},
+    },
+};
+
+type Socket = WebSocket<MaybeTlsStream<TcpStream>>;
+type Handler = Arc<dyn Fn(Vec<Value>) -> Result<(), Error> + Send + Sync>;
+type ErrorHandler = Arc<dyn Fn(Error) + Send + Sync>;
+
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+const SERVER_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long the worker blocks on a read before it checks for outbound messages again.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+enum Command {
+    Send(Messsage),
+    Stop,
+}
+
+struct Shared {
+    pending: Mutex<HashMap<String, mpsc::Sender<Result<Value, Error>>>>,
+    /// Handlers by lowercase target, with the id their [`Subscription`] removes them by.
+    listeners: Mutex<HashMap<String, Vec<(u64, Handler)>>>,
+    next_listener_id: AtomicU64,
+    error_handler: Mutex<Option<ErrorHandler>>,
+}
+
+impl Shared {
+    fn remove_listener(&self, key: &str, id: u64) {
+        let mut listeners = self.listeners.lock().unwrap();
+        if let Some(handlers) = listeners.get_mut(key) {
+            handlers.retain(|(handler_id, _)| *handler_id != id);
+            if handlers.is_empty() {
+                listeners.remove(key);
+            }
+        }
+    }
+
+    fn report_handler_error(&self, error: Error) {
+        let callback = self.error_handler.lock().unwrap().clone();
+        if let Some(callback) = callback {
+            callback(error);
+        }
+    }
+}
+
+/// A blocking connection to a SignalR hub.
+///
+/// Handlers registered with [`on`](HubConnection::on) run on the connection's worker thread,
+/// server invocations without a handler are yielded by [`messages`](HubConnection::messages).
+pub struct HubConnection {
+    shared: Arc<Shared>,
+    commands: mpsc::Sender<Command>,
+    incoming: Mutex<mpsc::Receiver<Messsage>>,
+    worker: Mutex<Option<JoinHandle<()>>>,
+    worker_id: ThreadId,
+    connection_id: String,
+    next_invocation_id: AtomicU64,
+}
+
+impl HubConnection {
+    /// Negotiates with `hub_url`, opens the WebSocket and performs the handshake.
+    pub fn connect(hub_url: &str) -> Result<HubConnection, Error> {
+        let client = reqwest::blocking::Client::builder()
+            .build()
+            .map_err(|e| Error::configuration_error(&e.to_string()))?;
+        let response = client.post(protocol::negotiate_url(hub_url))
+            .header("Content-Length", "0")
+            .send()
+            .map_err(|e| Error::negotiation_error(&e.to_string()))?;
+        if !response.status().is_success() {
+            return Err(Error::negotiation_error(&format!("server answered {}", response.status())));
+        }
+        let body = response.text().map_err(|e| Error::negotiation_error(&e.to_string()))?;
+        let negotiation = protocol::parse_negotiation(&body)?;
+
+        let url = protocol::websocket_url(hub_url, &negotiation.token)?;
+        let (mut socket, _) = tungstenite::connect(url.as_str())
+            .map_err(|e| Error::transport_error(&e.to_string()))?;
+        set_read_timeout(&socket, SERVER_TIMEOUT).map_err(|e| Error::transport_error(&e.to_string()))?;
+        let buffered = handshake(&mut socket)?;
+        set_read_timeout(&socket, POLL_INTERVAL).map_err(|e| Error::transport_error(&e.to_string()))?;
+
+        let shared = Arc::new(Shared {
+            pending: Mutex::new(HashMap::new()),
+            listeners: Mutex::new(HashMap::new()),
+            next_listener_id: AtomicU64::new(0),
+            error_handler: Mutex::new(None),
+        });
+        let (commands, commands_rx) = mpsc::channel();
+        let (incoming_tx, incoming) = mpsc::channel();
+        let worker = {
+            let shared = shared.clone();
+            thread::spawn(move || run(socket, shared, commands_rx, incoming_tx, buffered))
+        };
+
+        Ok(HubConnection {
+            shared,
+            commands,
+            incoming: Mutex::new(incoming),
+            worker_id: worker.thread().id(),
+            worker: Mutex::new(Some(worker)),
+            connection_id: negotiation.connection_id().to_owned(),
+            next_invocation_id: AtomicU64::new(0),
+        })
+    }
+
+    /// Id assigned by the server during negotiation.
+    pub fn connection_id(&self) -> &str {
+        &self.connection_id
+    }
+
+    /// Registers `handler` to be called on the worker thread when the server invokes `target`.
+    ///
+    /// The worker is blocked while the handler runs, so it must not wait on [`invoke`](HubConnection::invoke).
+    /// Calls whose arguments do not convert to `A` are reported to the
+    /// [`on_handler_error`](HubConnection::on_handler_error) callback and skipped.
+    /// Targets match regardless of case, every handler of a target is called, until the
+    /// returned [`Subscription`] is dropped.
+    pub fn on<A, F>(&self, target: &str, handler: F) -> Subscription
+    where
+        A: FromHubArgs,
+        F: Fn(A) + Send + Sync + 'static,
+    {
+        let key = listener_key(target);
+        let id = self.shared.next_listener_id.fetch_add(1, Ordering::Relaxed);
+        let handler = {
+            let target = target.to_owned();
+            move |arguments| {
+                handler(A::from_hub_args(&target, arguments)?);
+                Ok(())
+            }
+        };
+        self.shared
+            .listeners
+            .lock()
+            .unwrap()
+            .entry(key.clone())
+            .or_default()
+            .push((id, Arc::new(handler)));
+        Subscription {
+            shared: Some(Arc::downgrade(&self.shared)),
+            target: key,
+            id,
+        }
+    }
+
+    /// Unregisters every handler of `target`, their subscriptions no longer do anything.
+    pub fn off(&self, target: &str) {
+        self.shared.listeners.lock().unwrap().remove(&listener_key(target));
+    }
+
+    /// Sets the callback told about server invocations that could not be delivered to a handler,
+    /// it runs on the worker thread.
+    pub fn on_handler_error<F>(&self, callback: F)
+    where
+        F: Fn(Error) + Send + Sync + 'static,
+    {
+        *self.shared.error_handler.lock().unwrap() = Some(Arc::new(callback));
+    }
+
+    /// Invokes `target` on the server without waiting for a result.
+    pub fn send<A: IntoHubArgs>(&self, target: &str, arguments: A) -> Result<(), Error> {
+        self.send_message(Messsage::Invocation(InvocationFields::new(None, target, arguments.into_hub_args()?)))
+    }
+
+    /// Invokes `target` on the server and blocks until it completes.
+    pub fn invoke<A: IntoHubArgs>(&self, target: &str, arguments: A) -> Result<Value, Error> {
+        let arguments = arguments.into_hub_args()?;
+        let invocation_id = self.next_invocation_id.fetch_add(1, Ordering::Relaxed).to_string();
+        let (sender, receiver) = mpsc::channel();
+        self.shared
+            .pending
+            .lock()
+            .unwrap()
+            .insert(invocation_id.clone(), sender);
+
+        let message = Messsage::Invocation(InvocationFields::new(Some(invocation_id.clone()), target, arguments));
+        if let Err(error) = self.send_message(message) {
+            self.shared.pending.lock().unwrap().remove(&invocation_id);
+            return Err(error);
+        }
+
+        receiver
+            .recv()
+            .unwrap_or_else(|_| Err(Error::connection_closed_error(None)))
+    }
+
+    /// Blocking iterator over server invocations that have no registered handler.
+    ///
+    /// Ends once the connection is closed.
+    pub fn messages(&self) -> Messages<'_> {
+        Messages {
+            receiver: self.incoming.lock().unwrap(),
+        }
+    }
+
+    /// Sends a Close message, closes the WebSocket and waits for the worker thread to finish.
+    ///
+    /// Called from a handler, which runs on the worker, it returns without waiting and the worker
+    /// finishes once the handler does.
+    pub fn stop(&self) {
+        let _ = self.commands.send(Command::Stop);
+        if let Some(worker) = self.worker.lock().unwrap().take() {
+            if thread::current().id() != self.worker_id {
+                let _ = worker.join();
+            }
+        }
+    }
+
+    fn send_message(&self, message: Messsage) -> Result<(), Error> {
+        self.commands
+            .send(Command::Send(message))
+            .map_err(|_| Error::connection_closed_error(None))
+    }
+}
+
+impl Drop for HubConnection {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// A handler registered with [`HubConnection::on`], which stays registered as long as the
+/// subscription is kept.
+#[must_use = "the handler is unregistered when the subscription is dropped, call `detach` to keep it"]
+pub struct Subscription {
+    shared: Option<Weak<Shared>>,
+    target: String,
+    id: u64,
+}
+
+impl Subscription {
+    /// Unregisters the handler now, like dropping the subscription.
+    pub fn off(self) {}
+
+    /// Keeps the handler registered for as long as the connection lives.
+    pub fn detach(mut self) {
+        self.shared = None;
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        if let Some(shared) = self.shared.take().and_then(|shared| shared.upgrade()) {
+            shared.remove_listener(&self.target, self.id);
+        }
+    }
+}
+
+/// Iterator returned by [`HubConnection::messages`].
+pub struct Messages<'a> {
+    receiver: MutexGuard<'a, mpsc::Receiver<Messsage>>,
+}
+
+impl Iterator for Messages<'_> {
+    type Item = Messsage;
+
+    fn next(&mut self) -> Option<Messsage> {
+        self.receiver.recv().ok()
+    }
+}
+
+/// The worker polls the socket for reads, a stream it cannot set a timeout on would block it for good.
+fn set_read_timeout(socket: &Socket, timeout: Duration) -> io::Result<()> {
+    match socket.get_ref() {
+        MaybeTlsStream::Plain(stream) => stream.set_read_timeout(Some(timeout)),
+        MaybeTlsStream::NativeTls(stream) => stream.get_ref().set_read_timeout(Some(timeout)),
+        _ => Err(io::Error::new(
+            IoErrorKind::Unsupported,
+            "cannot set a read timeout on this TLS stream, only native-tls is supported",
+        )),
+    }
+}
+
+fn is_timeout(error: &tungstenite::Error) -> bool {
+    matches!(error, tungstenite::Error::Io(e) if matches!(e.kind(), IoErrorKind::WouldBlock | IoErrorKind::TimedOut))
+}
+
+fn handshake(socket: &mut Socket) -> Result<Vec<Messsage>, Error> {
+    socket
+        .write_message(Message::Text(protocol::handshake_request()))
+        .map_err(|e| Error::handshake_error(&e.to_string()))?;
+    loop {
+        match socket.read_message() {
+            Ok(Message::Text(text)) => return protocol::parse_handshake_response(&text),
+            Ok(Message::Close(_)) => return Err(Error::handshake_error_simple()),
+            Ok(_) => continue,
+            Err(e) => return Err(Error::handshake_error(&e.to_string())),
+        }
+    }
+}
+
+fn write(socket: &mut Socket, message: Messsage) -> bool {
+    match message.serialize() {
+        Some(text) => socket.write_message(Message::Text(text)).is_ok(),
+        None => true,
+    }
+}
+
+/// Handles one inbound message, returns the close reason when the server closed the connection.
+fn dispatch(shared: &Shared, incoming: &mpsc::Sender<Messsage>, message: Messsage) -> Option<CloseFields> {
+    match message {
+        Messsage::Invocation(fields) => {
+            let handlers = shared.listeners.lock().unwrap().get(&listener_key(&fields.target)).cloned();
+            match handlers {
+                Some(handlers) => {
+                    for (_, handler) in handlers {
+                        if let Err(error) = handler(fields.arguments.clone()) {
+                            shared.report_handler_error(error);
+                        }
+                    }
+                }
+                None => {
+                    let _ = incoming.send(Messsage::Invocation(fields));
+                }
+            }
+        }
+        Messsage::Completion(CompletionFields {
+            invocation_id,
+            error,
+            result,
+            ..
+        }) => {
+            let pending = shared.pending.lock().unwrap().remove(&invocation_id);
+            if let Some(pending) = pending {
+                let _ = pending.send(match error {
+                    Some(error) => Err(Error::hub_error(&error)),
+                    None => Ok(result.unwrap_or(Value::Null)),
+                });
+            }
+        }
+        Messsage::Close(fields) => return Some(fields),
+        _ => {}
+    }
+    None
+}
+
+fn run(
+    mut socket: Socket,
+    shared: Arc<Shared>,
+    commands: mpsc::Receiver<Command>,
+    incoming: mpsc::Sender<Messsage>,
+    buffered: Vec<Messsage>,
+) {
+    let mut last_read = Instant::now();
+    let mut last_write = Instant::now();
+    let mut close = buffered
+        .into_iter()
+        .find_map(|message| dispatch(&shared, &incoming, message));
+
+    'run: while close.is_none() {
+        loop {
+            match commands.try_recv() {
+                Ok(Command::Send(message)) => {
+                    if !write(&mut socket, message) {
+                        break 'run;
+                    }
+                    last_write = Instant::now();
+                }
+                Ok(Command::Stop) | Err(TryRecvError::Disconnected) => {
+                    write(&mut socket, Messsage::Close(CloseFields::new(None, false)));
+                    break 'run;
+                }
+                Err(TryRecvError::Empty) => break,
+            }
+        }
+
+        if last_write.elapsed() >= KEEP_ALIVE_INTERVAL {
+            if !write(&mut socket, Messsage::Ping) {
+                break;
+            }
+            last_write = Instant::now();
+        }
+
+        match socket.read_message() {
+            Ok(Message::Text(text)) => {
+                last_read = Instant::now();
+                close = protocol::parse_messages(&text).find_map(|message| dispatch(&shared, &incoming, message));
+            }
+            Ok(Message::Close(_)) => break,
+            Ok(_) => last_read = Instant::now(),
+            Err(ref e) if is_timeout(e) => {
+                if last_read.elapsed() >= SERVER_TIMEOUT {
+                    break;
+                }
+            }
+            Err(_) => break,
+        }
+    }
+
+    // Finish the close handshake, bounded in case the server never answers.
+    let _ = socket.close(Some(runtime::normal_closure()));
+    let closing = Instant::now();
+    while closing.elapsed() < SERVER_TIMEOUT {
+        match socket.read_message() {
+            Ok(_) => continue,
+            Err(ref e) if is_timeout(e) => continue,
+            Err(_) => break,
+        }
+    }
+
+    // No new invocation can be queued once the command channel is gone, fail whatever is left.
+    drop(commands);
+    let reason = close.and_then(|fields| fields.error);
+    let pending: Vec<_> = shared.pending.lock().unwrap().drain().collect();
+    for (_, sender) in pending {
+        let _ = sender.send(Err(Error::connection_closed_error(reason.as_deref())));
+    }
+}
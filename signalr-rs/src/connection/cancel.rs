@@ -0,0 +1,52 @@
+use std::{
+    pin::pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use tokio::sync::Notify;
+
+#[derive(Debug, Default)]
+struct State {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+/// Cancels the calls it is passed to through [`InvocationOptions`](super::InvocationOptions).
+///
+/// Clones share the same state, cancelling any of them cancels every call given one.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken {
+    state: Arc<State>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cancels the calls holding the token, now and for any call it is given later.
+    pub fn cancel(&self) {
+        self.state.cancelled.store(true, Ordering::SeqCst);
+        self.state.notify.notify_waiters();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.state.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Resolves once the token is cancelled.
+    pub async fn cancelled(&self) {
+        loop {
+            let mut notified = pin!(self.state.notify.notified());
+            // Registered before checking the flag so a concurrent `cancel` is not missed.
+            notified.as_mut().enable();
+            if self.is_cancelled() {
+                return;
+            }
+            notified.await;
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use crate::protocol::responses::Headers;
+
+use super::HubConnection;
+
+/// The server invocation a handler registered with
+/// [`on_with_context`](HubConnection::on_with_context) is answering.
+#[derive(Clone)]
+pub struct HubCallContext {
+    pub(crate) connection: HubConnection,
+    pub(crate) connection_id: Option<String>,
+    pub(crate) target: String,
+    pub(crate) invocation_id: Option<String>,
+    pub(crate) headers: Headers,
+}
+
+impl HubCallContext {
+    /// The connection the invocation arrived on, to reply or invoke further methods.
+    pub fn connection(&self) -> &HubConnection {
+        &self.connection
+    }
+
+    /// Id the server assigned to the connection during negotiation.
+    pub fn connection_id(&self) -> Option<&str> {
+        self.connection_id.as_deref()
+    }
+
+    /// The hub method the server invoked.
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    /// Set when the server expects a result for the invocation.
+    pub fn invocation_id(&self) -> Option<&str> {
+        self.invocation_id.as_deref()
+    }
+
+    /// Headers sent along with the invocation.
+    pub fn headers(&self) -> &Headers {
+        &self.headers
+    }
+}
@@ -0,0 +1,91 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use tokio::sync::{mpsc, Semaphore};
+
+use crate::runtime::{BoxFuture, Runtime};
+
+/// How the handlers of server invocations run relative to each other.
+///
+/// Stream items and completions do not wait for handlers, whatever the mode, as long as fewer
+/// handler calls than the inbound capacity are waiting to finish. Once that many are, the
+/// connection stops reading, and the results of `invoke` and `stream` wait with the rest.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum DispatchMode {
+    /// One handler at a time, in the order the invocations arrived, like the .NET client.
+    #[default]
+    Sequential,
+    /// Up to this many handlers at a time, in no particular order.
+    Parallel(usize),
+    /// One handler at a time for each target, handlers of different targets run concurrently.
+    PerTarget,
+}
+
+type Call = BoxFuture<'static, ()>;
+
+/// Runs handler calls as the [`DispatchMode`] orders them.
+pub(crate) struct HandlerScheduler {
+    mode: DispatchMode,
+    runtime: Arc<dyn Runtime>,
+    /// Calls scheduled and not finished yet, bounded by the inbound capacity.
+    backlog: Arc<Semaphore>,
+    /// Calls running at once in `Parallel` mode.
+    running: Arc<Semaphore>,
+    /// The queue of each worker running calls in order, keyed by target in `PerTarget` mode.
+    workers: Mutex<HashMap<String, mpsc::UnboundedSender<Call>>>,
+}
+
+impl HandlerScheduler {
+    pub(crate) fn new(mode: DispatchMode, capacity: usize, runtime: Arc<dyn Runtime>) -> Self {
+        let parallelism = match mode {
+            DispatchMode::Parallel(parallelism) => parallelism.max(1),
+            _ => 1,
+        };
+        HandlerScheduler {
+            mode,
+            runtime,
+            backlog: Arc::new(Semaphore::new(capacity.max(1))),
+            running: Arc::new(Semaphore::new(parallelism)),
+            workers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Schedules the handler `call` of `target`, waiting while the backlog is full.
+    pub(crate) async fn schedule(&self, target: &str, call: Call) {
+        let permit = self.backlog.clone().acquire_owned().await.ok();
+        let call: Call = Box::pin(async move {
+            call.await;
+            drop(permit);
+        });
+        match self.mode {
+            DispatchMode::Sequential => self.enqueue("", call),
+            DispatchMode::PerTarget => self.enqueue(target, call),
+            DispatchMode::Parallel(_) => {
+                let running = self.running.clone();
+                self.runtime.spawn(Box::pin(async move {
+                    let _running = running.acquire_owned().await;
+                    call.await;
+                }));
+            }
+        }
+    }
+
+    fn enqueue(&self, key: &str, call: Call) {
+        let mut workers = self.workers.lock().unwrap();
+        let worker = workers.entry(key.to_owned()).or_insert_with(|| {
+            let (sender, calls) = mpsc::unbounded_channel();
+            self.runtime.spawn(Box::pin(run_in_order(calls)));
+            sender
+        });
+        let _ = worker.send(call);
+    }
+}
+
+/// Runs `calls` one after the other, until the scheduler is dropped.
+async fn run_in_order(mut calls: mpsc::UnboundedReceiver<Call>) {
+    while let Some(call) = calls.recv().await {
+        call.await;
+    }
+}
@@ -0,0 +1,1026 @@
+mod cancel;
+mod context;
+mod dispatch;
+mod observer;
+mod queue;
+mod receiver;
+mod state;
+mod subscription;
+mod trace;
+
+pub use cancel::CancellationToken;
+pub use context::HubCallContext;
+pub use dispatch::DispatchMode;
+pub use observer::ConnectionObserver;
+pub use queue::OutboundPolicy;
+use dispatch::HandlerScheduler;
+use queue::{Outbound, OutboundQueue};
+pub use receiver::{HubReceiver, ReceiverHandler};
+pub use state::HubConnectionState;
+pub use subscription::Subscription;
+
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::{pin, Pin},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, Weak,
+    },
+    task::{ready, Context, Poll},
+    time::{Duration, Instant},
+};
+
+use futures_util::{
+    future::{self, Either},
+    stream, SinkExt, Stream, StreamExt,
+};
+use serde_json::Value;
+use tokio::sync::{broadcast, mpsc, oneshot, watch};
+
+use crate::{
+    diagnostics::{self, event},
+    error::Error,
+    protocol::{
+        self,
+        arguments::{FromHubArgs, IntoHubArgs},
+        responses::{
+            CancelInvokationFields, CloseFields, CompletionFields, Headers, InvocationFields, Messsage,
+            StreamInvocationFields,
+        },
+    },
+    runtime::{self, BoxFuture, Frame, Runtime, TaskHandle, Transport, TransportSink, TransportStream},
+};
+
+#[derive(Clone)]
+pub(crate) struct ConnectionOptions {
+    pub(crate) hub_url: String,
+    pub(crate) keep_alive_interval: Duration,
+    pub(crate) server_timeout: Duration,
+    pub(crate) reconnect_delays: Vec<Duration>,
+    pub(crate) log_payloads: bool,
+    pub(crate) observer: Option<Arc<dyn ConnectionObserver>>,
+    pub(crate) outbound_capacity: usize,
+    pub(crate) outbound_policy: OutboundPolicy,
+    pub(crate) inbound_capacity: usize,
+    pub(crate) dispatch_mode: DispatchMode,
+    pub(crate) warn_unhandled: bool,
+}
+
+type ErrorHandler = Arc<dyn Fn(Error) + Send + Sync>;
+type UnhandledHandler = Arc<dyn Fn(&str, Vec<Value>) + Send + Sync>;
+
+/// A registered handler and the id its [`Subscription`] removes it by.
+type Listener = (u64, Arc<dyn Executable>);
+
+/// A handler of server invocations, returning the call the scheduler runs.
+trait Executable: Send + Sync {
+    fn execute(&self, context: HubCallContext, arguments: Vec<Value>) -> Result<BoxFuture<'static, ()>, Error>;
+}
+
+impl<F> Executable for F
+where
+    F: Fn(HubCallContext, Vec<Value>) -> Result<BoxFuture<'static, ()>, Error> + Send + Sync,
+{
+    fn execute(&self, context: HubCallContext, arguments: Vec<Value>) -> Result<BoxFuture<'static, ()>, Error> {
+        self(context, arguments)
+    }
+}
+
+/// Options of a single [`send_with`](HubConnection::send_with),
+/// [`invoke_with`](HubConnection::invoke_with) or [`stream_with`](HubConnection::stream_with) call.
+#[derive(Clone, Debug, Default)]
+pub struct InvocationOptions {
+    /// Sent along with the invocation, for tracing context and other metadata.
+    pub headers: Headers,
+    /// Fails the call with a `Timeout` error once elapsed, for a stream the whole stream.
+    pub timeout: Option<Duration>,
+    /// Fails the call with a `Cancelled` error once cancelled.
+    pub cancellation: Option<CancellationToken>,
+}
+
+enum PendingInvocation {
+    Invoke(oneshot::Sender<Result<Value, Error>>),
+    Stream {
+        items: mpsc::Sender<Value>,
+        error: oneshot::Sender<Error>,
+    },
+}
+
+impl PendingInvocation {
+    fn complete(self, result: Result<Value, Error>) {
+        match self {
+            PendingInvocation::Invoke(sender) => {
+                let _ = sender.send(result);
+            }
+            PendingInvocation::Stream { error, .. } => {
+                // A successful completion simply ends the stream once the senders are dropped.
+                if let Err(result) = result {
+                    let _ = error.send(result);
+                }
+            }
+        }
+    }
+}
+
+/// Targets match regardless of case, as they do on ASP.NET Core hubs.
+pub(crate) fn listener_key(target: &str) -> String {
+    target.to_lowercase()
+}
+
+/// What the reader of a session hands to its dispatcher.
+enum Inbound {
+    Message(Messsage),
+    Lost(CloseReason),
+}
+
+/// Outcome of handling a single inbound message.
+enum Dispatch {
+    Continue,
+    Close(CloseFields),
+}
+
+/// Why a running transport went away.
+struct CloseReason {
+    error: Option<String>,
+    allow_reconnect: bool,
+}
+
+impl CloseReason {
+    fn transport(error: &str) -> Self {
+        CloseReason {
+            error: Some(error.to_owned()),
+            allow_reconnect: true,
+        }
+    }
+}
+
+/// The tasks and channel backing one successfully started transport.
+struct Session {
+    id: u64,
+    outbound: Outbound,
+    writer: TaskHandle,
+    reader: TaskHandle,
+}
+
+/// A connection to a SignalR hub.
+///
+/// Cloning is cheap, every clone drives the same underlying connection.
+#[derive(Clone)]
+pub struct HubConnection {
+    inner: Arc<ConnectionInner>,
+}
+
+struct ConnectionInner {
+    options: ConnectionOptions,
+    runtime: Arc<dyn Runtime>,
+    transport: Arc<dyn Transport>,
+    state: watch::Sender<HubConnectionState>,
+    connection_id: Mutex<Option<String>>,
+    session: Mutex<Option<Session>>,
+    pending: Mutex<HashMap<String, PendingInvocation>>,
+    listeners: Mutex<HashMap<String, Vec<Listener>>>,
+    error_handler: Mutex<Option<ErrorHandler>>,
+    unhandled: Mutex<Option<UnhandledHandler>>,
+    received: broadcast::Sender<Messsage>,
+    handlers: HandlerScheduler,
+    next_invocation_id: AtomicU64,
+    next_session_id: AtomicU64,
+    next_listener_id: AtomicU64,
+}
+
+impl HubConnection {
+    pub(crate) fn new(
+        options: ConnectionOptions,
+        runtime: Arc<dyn Runtime>,
+        transport: Arc<dyn Transport>,
+    ) -> Self {
+        let (state, _) = watch::channel(HubConnectionState::Disconnected);
+        let (received, _) = broadcast::channel(options.inbound_capacity.max(1));
+        let handlers = HandlerScheduler::new(options.dispatch_mode, options.inbound_capacity, runtime.clone());
+        HubConnection {
+            inner: Arc::new(ConnectionInner {
+                options,
+                runtime,
+                transport,
+                state,
+                connection_id: Mutex::new(None),
+                session: Mutex::new(None),
+                pending: Mutex::new(HashMap::new()),
+                listeners: Mutex::new(HashMap::new()),
+                error_handler: Mutex::new(None),
+                unhandled: Mutex::new(None),
+                received,
+                handlers,
+                next_invocation_id: AtomicU64::new(0),
+                next_session_id: AtomicU64::new(0),
+                next_listener_id: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Current state of the connection.
+    pub fn state(&self) -> HubConnectionState {
+        *self.inner.state.borrow()
+    }
+
+    /// Subscribes to state changes, the receiver always observes the latest state.
+    pub fn state_changes(&self) -> watch::Receiver<HubConnectionState> {
+        self.inner.state.subscribe()
+    }
+
+    /// Id assigned by the server during negotiation, if the connection was ever started.
+    pub fn connection_id(&self) -> Option<String> {
+        self.inner.connection_id.lock().unwrap().clone()
+    }
+
+    /// Negotiates, opens the transport and performs the handshake.
+    ///
+    /// Only valid while the connection is `Disconnected`.
+    pub async fn start(&self) -> Result<(), Error> {
+        self.inner.transition(HubConnectionState::Connecting)?;
+        let result = self.inner.connect().await;
+        if let Err(error) = &result {
+            event!(warn, %error, "failed to start the connection");
+            let _ = self.inner.transition(HubConnectionState::Disconnected);
+        }
+        result
+    }
+
+    /// Gracefully stops the connection.
+    ///
+    /// Sends a Close message, flushes everything queued before it, closes the transport with a
+    /// WebSocket close frame and fails every pending invocation and stream with a
+    /// `ConnectionClosed` error. Stopping an automatic reconnect in progress ends it.
+    pub async fn stop(&self) -> Result<(), Error> {
+        self.inner.transition(HubConnectionState::Disconnecting)?;
+        event!(info, "stopping the connection");
+        let session = self.inner.session.lock().unwrap().take();
+        if let Some(session) = session {
+            session.outbound.queue().push_unbounded(Messsage::Close(CloseFields::new(None, false)));
+            drop(session.outbound);
+            // One deadline for the whole shutdown: the writer flushes the Close message, then the
+            // reader waits for the server to close the socket.
+            let (mut writer, mut reader) = (session.writer, session.reader);
+            let shutdown = async {
+                (&mut writer).await;
+                (&mut reader).await;
+            };
+            if runtime::timeout(&*self.inner.runtime, self.inner.options.server_timeout, shutdown).await.is_err() {
+                writer.abort();
+                reader.abort();
+            }
+        }
+        self.inner.fail_pending(&Error::connection_closed_error(None));
+        let _ = self.inner.transition(HubConnectionState::Disconnected);
+        Ok(())
+    }
+
+    /// Registers `handler` to be called when the server invokes `target`.
+    ///
+    /// The arguments are converted to `A`, a tuple such as `(String, u32)` or `Vec<Value>` to take
+    /// them as they are; calls that do not convert are reported to the
+    /// [`on_handler_error`](HubConnection::on_handler_error) callback and skipped.
+    /// Targets match regardless of case, every handler of a target is called. Handlers run as
+    /// the [`DispatchMode`] of the connection allows, until the returned [`Subscription`] is
+    /// dropped.
+    pub fn on<A, F>(&self, target: &str, handler: F) -> Subscription
+    where
+        A: FromHubArgs + Send + 'static,
+        F: Fn(A) + Send + Sync + 'static,
+    {
+        self.on_with_headers(target, move |_: &Headers, arguments| handler(arguments))
+    }
+
+    /// Like [`on`](HubConnection::on), `handler` also receives the headers of the invocation.
+    pub fn on_with_headers<A, F>(&self, target: &str, handler: F) -> Subscription
+    where
+        A: FromHubArgs + Send + 'static,
+        F: Fn(&Headers, A) + Send + Sync + 'static,
+    {
+        let handler = Arc::new(handler);
+        self.listen(target, move |context: HubCallContext, arguments: Vec<Value>| {
+            let arguments = A::from_hub_args(&context.target, arguments)?;
+            let handler = handler.clone();
+            let call: BoxFuture<'static, ()> = Box::pin(async move { handler(&context.headers, arguments) });
+            Ok(call)
+        })
+    }
+
+    /// Like [`on`](HubConnection::on) for a `handler` returning a future, which does not hold up
+    /// stream items and completions while it runs.
+    pub fn on_async<A, F, R>(&self, target: &str, handler: F) -> Subscription
+    where
+        A: FromHubArgs,
+        F: Fn(A) -> R + Send + Sync + 'static,
+        R: Future<Output = ()> + Send + 'static,
+    {
+        self.listen(target, move |context: HubCallContext, arguments: Vec<Value>| {
+            let call: BoxFuture<'static, ()> = Box::pin(handler(A::from_hub_args(&context.target, arguments)?));
+            Ok(call)
+        })
+    }
+
+    /// Like [`on_async`](HubConnection::on_async), `handler` also receives the
+    /// [`HubCallContext`] of the invocation, with the connection to reply through.
+    pub fn on_with_context<A, F, R>(&self, target: &str, handler: F) -> Subscription
+    where
+        A: FromHubArgs,
+        F: Fn(HubCallContext, A) -> R + Send + Sync + 'static,
+        R: Future<Output = ()> + Send + 'static,
+    {
+        self.listen(target, move |context: HubCallContext, arguments: Vec<Value>| {
+            let arguments = A::from_hub_args(&context.target, arguments)?;
+            let call: BoxFuture<'static, ()> = Box::pin(handler(context, arguments));
+            Ok(call)
+        })
+    }
+
+    /// Unregisters every handler of `target`, their subscriptions no longer do anything.
+    pub fn off(&self, target: &str) {
+        self.inner.listeners.lock().unwrap().remove(&listener_key(target));
+    }
+
+    fn listen<E: Executable + 'static>(&self, target: &str, executable: E) -> Subscription {
+        let key = listener_key(target);
+        let id = self.inner.next_listener_id.fetch_add(1, Ordering::Relaxed);
+        self.inner
+            .listeners
+            .lock()
+            .unwrap()
+            .entry(key.clone())
+            .or_default()
+            .push((id, Arc::new(executable)));
+        Subscription::new(Arc::downgrade(&self.inner), key, id)
+    }
+
+    /// Registers every method of `receiver` for as long as the connection lives, see
+    /// `#[hub_receiver]`.
+    ///
+    /// Calls whose arguments do not match the method's parameters are reported to the
+    /// [`on_handler_error`](HubConnection::on_handler_error) callback and skipped.
+    pub fn register<R: HubReceiver>(&self, receiver: R) {
+        for handler in Arc::new(receiver).handlers() {
+            let target = handler.target();
+            self.listen(target, move |_: HubCallContext, arguments: Vec<Value>| handler.call(arguments))
+                .detach();
+        }
+    }
+
+    /// Sets the callback told about server invocations that could not be delivered to a handler.
+    pub fn on_handler_error<F>(&self, callback: F)
+    where
+        F: Fn(Error) + Send + Sync + 'static,
+    {
+        *self.inner.error_handler.lock().unwrap() = Some(Arc::new(callback));
+    }
+
+    /// Sets the callback told about server invocations of targets without a handler, with the
+    /// target and the arguments as they were received. It runs on the dispatcher and must not block.
+    pub fn on_unhandled<F>(&self, callback: F)
+    where
+        F: Fn(&str, Vec<Value>) + Send + Sync + 'static,
+    {
+        *self.inner.unhandled.lock().unwrap() = Some(Arc::new(callback));
+    }
+
+    /// Every message received from the server from now on, before it is dispatched, for
+    /// debugging and proxying tools.
+    ///
+    /// A subscriber falling more than the inbound capacity behind misses the oldest messages.
+    pub fn messages(&self) -> MessageStream {
+        let receiver = self.inner.received.subscribe();
+        let messages = stream::unfold(receiver, |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(message) => return Some((message, receiver)),
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        });
+        MessageStream { messages: Box::pin(messages) }
+    }
+
+    /// Invokes `target` on the server without waiting for a result.
+    pub async fn send<A: IntoHubArgs>(&self, target: &str, arguments: A) -> Result<(), Error> {
+        self.send_with(target, arguments, InvocationOptions::default()).await
+    }
+
+    /// [`send`](HubConnection::send) with per call `options`.
+    pub async fn send_with<A: IntoHubArgs>(&self, target: &str, arguments: A, mut options: InvocationOptions) -> Result<(), Error> {
+        let span = trace::outbound("send", target, &mut options.headers);
+        let interruption = self.inner.interruption(&options);
+        let send = async move {
+            let arguments = arguments.into_hub_args()?;
+            let invocation = InvocationFields::new(None, target, arguments).with_headers(options.headers);
+            interruptible(self.inner.send_message(Messsage::Invocation(invocation)), interruption).await
+        };
+        trace::instrument(send, span).await
+    }
+
+    /// Invokes `target` on the server and waits for its completion.
+    pub async fn invoke<A: IntoHubArgs>(&self, target: &str, arguments: A) -> Result<Value, Error> {
+        self.invoke_with(target, arguments, InvocationOptions::default()).await
+    }
+
+    /// [`invoke`](HubConnection::invoke) with per call `options`.
+    pub async fn invoke_with<A: IntoHubArgs>(&self, target: &str, arguments: A, mut options: InvocationOptions) -> Result<Value, Error> {
+        let span = trace::outbound("invoke", target, &mut options.headers);
+        let interruption = self.inner.interruption(&options);
+        let invoke = async move {
+            let arguments = arguments.into_hub_args()?;
+            let started = self.inner.options.observer.as_ref().map(|_| Instant::now());
+            let invocation_id = self.inner.next_invocation_id();
+            let (sender, receiver) = oneshot::channel();
+            self.inner.register_pending(&invocation_id, PendingInvocation::Invoke(sender));
+            // Frees the slot when the call fails, is interrupted or its future is dropped.
+            let _pending = PendingSlot::new(&self.inner, &invocation_id);
+
+            let invocation = InvocationFields::new(Some(invocation_id.clone()), target, arguments).with_headers(options.headers);
+            let message = Messsage::Invocation(invocation);
+            let call = async {
+                self.inner.send_message(message).await?;
+                receiver
+                    .await
+                    .unwrap_or_else(|_| Err(Error::connection_closed_error(None)))
+            };
+            let result = interruptible(call, interruption).await;
+            if let Some(started) = started {
+                self.inner
+                    .observe(|observer| observer.invocation_completed(target, started.elapsed(), result.is_ok()));
+            }
+            result
+        };
+        trace::instrument(invoke, span).await
+    }
+
+    /// Invokes a streaming method on the server, items arrive through the returned stream.
+    ///
+    /// Up to the inbound capacity of items wait for the stream to be polled, past that the
+    /// connection stops reading until it is, so poll the stream or drop it.
+    pub async fn stream<A: IntoHubArgs>(&self, target: &str, arguments: A) -> Result<HubStream, Error> {
+        self.stream_with(target, arguments, InvocationOptions::default()).await
+    }
+
+    /// [`stream`](HubConnection::stream) with per call `options`.
+    pub async fn stream_with<A: IntoHubArgs>(&self, target: &str, arguments: A, mut options: InvocationOptions) -> Result<HubStream, Error> {
+        let span = trace::outbound("stream", target, &mut options.headers);
+        let mut interruption = self.inner.interruption(&options);
+        let stream = async move {
+            let arguments = arguments.into_hub_args()?;
+            let invocation_id = self.inner.next_invocation_id();
+            let (items, receiver) = mpsc::channel(self.inner.options.inbound_capacity.max(1));
+            let (error, failure) = oneshot::channel();
+            self.inner.register_pending(&invocation_id, PendingInvocation::Stream { items, error });
+            // Until the server has the invocation there is nothing to cancel, only the slot to free.
+            let pending = PendingSlot::new(&self.inner, &invocation_id);
+
+            let message = Messsage::StreamInvocation(
+                StreamInvocationFields::new(&invocation_id, target, arguments).with_headers(options.headers),
+            );
+            interruptible(self.inner.send_message(message), interruption.as_mut()).await?;
+            pending.release();
+
+            Ok(HubStream {
+                receiver,
+                failure: Some(failure),
+                interruption: Some(interruption),
+                invocation: Some(StreamInvocation {
+                    connection: Arc::downgrade(&self.inner),
+                    invocation_id,
+                }),
+            })
+        };
+        trace::instrument(stream, span).await
+    }
+}
+
+impl ConnectionInner {
+    /// Moves to `next` if allowed from the current state, returning the previous state.
+    fn transition(&self, next: HubConnectionState) -> Result<HubConnectionState, Error> {
+        let mut result = Err(Error::invalid_state_error(next));
+        self.state.send_if_modified(|current| {
+            if current.can_transition_to(next) {
+                event!(debug, from = %current, to = %next, "connection state changed");
+                result = Ok(*current);
+                *current = next;
+                true
+            } else {
+                result = Err(Error::invalid_state_error(*current));
+                false
+            }
+        });
+        result
+    }
+
+    async fn connect(self: &Arc<Self>) -> Result<(), Error> {
+        event!(debug, hub_url = %self.options.hub_url, "negotiating");
+        let negotiation = protocol::start_negotiation(&*self.transport, &self.options.hub_url).await?;
+        event!(
+            debug,
+            connection_id = negotiation.connection_id(),
+            offered = ?negotiation.transports().collect::<Vec<_>>(),
+            "negotiated, selected the WebSockets transport"
+        );
+        let url = protocol::websocket_url(&self.options.hub_url, &negotiation.token)?;
+        let (mut writer, mut reader) = self.transport.connect(&url).await?;
+        let handshake = handshake(&mut writer, &mut reader);
+        let buffered = runtime::timeout(&*self.runtime, self.options.server_timeout, handshake)
+            .await
+            .map_err(|_| Error::handshake_error("timed out waiting for the handshake response"))
+            .and_then(|response| response)
+            .inspect_err(|error| self.observe(|observer| observer.handshake_failed(error)))?;
+        event!(debug, "handshake completed");
+
+        let queue = Arc::new(OutboundQueue::new(self.options.outbound_capacity, self.options.outbound_policy));
+        let (inbound, inbound_rx) = mpsc::channel(self.options.inbound_capacity.max(1));
+        let mut session = self.session.lock().unwrap();
+        // `stop` may have been called while the transport was being established.
+        if let Err(error) = self.transition(HubConnectionState::Connected) {
+            self.runtime.spawn(Box::pin(async move { close_transport(&mut writer).await }));
+            return Err(error);
+        }
+
+        let id = self.next_session_id.fetch_add(1, Ordering::Relaxed);
+        event!(info, connection_id = negotiation.connection_id(), "connected");
+        *self.connection_id.lock().unwrap() = Some(negotiation.connection_id().to_owned());
+        self.runtime.spawn(Box::pin(dispatch_loop(inbound_rx, Arc::downgrade(self), id)));
+        *session = Some(Session {
+            id,
+            outbound: Outbound::new(queue.clone()),
+            writer: runtime::spawn(
+                &*self.runtime,
+                write_loop(
+                    writer,
+                    queue,
+                    self.runtime.clone(),
+                    self.options.keep_alive_interval,
+                    self.options.log_payloads,
+                    self.options.observer.clone(),
+                ),
+            ),
+            reader: runtime::spawn(
+                &*self.runtime,
+                read_loop(reader, inbound, self.runtime.clone(), self.options.clone(), buffered),
+            ),
+        });
+        Ok(())
+    }
+
+    /// Retries `connect` with the configured delays until it succeeds, gives up or is stopped.
+    async fn reconnect(self: Arc<Self>) {
+        for (attempt, delay) in self.options.reconnect_delays.iter().enumerate() {
+            event!(info, attempt = attempt + 1, ?delay, "reconnecting");
+            self.runtime.sleep(*delay).await;
+            if *self.state.borrow() != HubConnectionState::Reconnecting {
+                return;
+            }
+            let result = self.connect().await;
+            self.observe(|observer| observer.reconnect_attempt(attempt + 1, result.is_ok()));
+            match result {
+                Ok(()) => return,
+                Err(error) => event!(warn, attempt = attempt + 1, %error, "reconnect attempt failed"),
+            }
+        }
+        event!(error, "every reconnect attempt failed, giving up");
+        let _ = self.transition(HubConnectionState::Disconnected);
+    }
+
+    /// Resolves with the error ending a call once its timeout elapses or its token is cancelled.
+    fn interruption(&self, options: &InvocationOptions) -> BoxFuture<'static, Error> {
+        let timeout = options.timeout.map(|timeout| (timeout, self.runtime.sleep(timeout)));
+        let cancellation = options.cancellation.clone();
+        Box::pin(async move {
+            let timed_out = pin!(async move {
+                match timeout {
+                    Some((timeout, sleep)) => {
+                        sleep.await;
+                        Error::timeout_error(timeout)
+                    }
+                    None => future::pending().await,
+                }
+            });
+            let cancelled = pin!(async move {
+                match cancellation {
+                    Some(token) => {
+                        token.cancelled().await;
+                        Error::cancelled_error()
+                    }
+                    None => future::pending().await,
+                }
+            });
+            future::select(timed_out, cancelled).await.factor_first().0
+        })
+    }
+
+    /// Asks the server to stop the stream `invocation_id`, unless it already completed it.
+    fn cancel_stream(&self, invocation_id: &str) {
+        if self.remove_pending(invocation_id).is_none() {
+            return;
+        }
+        if let Some(session) = self.session.lock().unwrap().as_ref() {
+            let cancel = Messsage::CancelInvokation(CancelInvokationFields::new(invocation_id));
+            session.outbound.queue().push_unbounded(cancel);
+        }
+    }
+
+    fn next_invocation_id(&self) -> String {
+        self.next_invocation_id.fetch_add(1, Ordering::Relaxed).to_string()
+    }
+
+    fn register_pending(&self, invocation_id: &str, pending: PendingInvocation) {
+        let count = {
+            let mut invocations = self.pending.lock().unwrap();
+            invocations.insert(invocation_id.to_owned(), pending);
+            invocations.len()
+        };
+        self.observe(|observer| observer.pending_invocations(count));
+    }
+
+    fn remove_pending(&self, invocation_id: &str) -> Option<PendingInvocation> {
+        let (pending, count) = {
+            let mut invocations = self.pending.lock().unwrap();
+            (invocations.remove(invocation_id), invocations.len())
+        };
+        if pending.is_some() {
+            self.observe(|observer| observer.pending_invocations(count));
+        }
+        pending
+    }
+
+    /// Reports to the observer, if the connection has one.
+    fn observe<F: FnOnce(&dyn ConnectionObserver)>(&self, report: F) {
+        if let Some(observer) = &self.options.observer {
+            report(&**observer);
+        }
+    }
+
+    /// Queues `message` on the current session, as the outbound policy allows.
+    async fn send_message(&self, message: Messsage) -> Result<(), Error> {
+        let state = *self.state.borrow();
+        if state != HubConnectionState::Connected {
+            return Err(Error::invalid_state_error(state));
+        }
+        let queue = match self.session.lock().unwrap().as_ref() {
+            Some(session) => session.outbound.queue(),
+            None => return Err(Error::invalid_state_error(state)),
+        };
+        queue.push(message).await
+    }
+
+    async fn dispatch(self: &Arc<Self>, message: Messsage) -> Dispatch {
+        match message {
+            Messsage::Invocation(InvocationFields {
+                headers,
+                invocation_id,
+                target,
+                arguments,
+                ..
+            }) => {
+                let key = listener_key(&target);
+                let handlers = self.listeners.lock().unwrap().get(&key).cloned().unwrap_or_default();
+                if handlers.is_empty() {
+                    diagnostics::unhandled(&target, self.options.warn_unhandled);
+                    let callback = self.unhandled.lock().unwrap().clone();
+                    if let Some(callback) = callback {
+                        callback(&target, arguments);
+                    }
+                    return Dispatch::Continue;
+                }
+                let calls: Vec<_> = {
+                    let _scope = trace::inbound(&target, &headers);
+                    let context = HubCallContext {
+                        connection: HubConnection { inner: self.clone() },
+                        connection_id: self.connection_id.lock().unwrap().clone(),
+                        target: target.clone(),
+                        invocation_id,
+                        headers,
+                    };
+                    handlers
+                        .into_iter()
+                        .filter_map(|(_, handler)| match handler.execute(context.clone(), arguments.clone()) {
+                            Ok(call) => Some(trace::bind(call)),
+                            Err(error) => {
+                                event!(warn, target = %target, %error, "the handler rejected an invocation");
+                                self.report_handler_error(error);
+                                None
+                            }
+                        })
+                        .collect()
+                };
+                for call in calls {
+                    self.handlers.schedule(&key, call).await;
+                }
+            }
+            Messsage::StreamItem(fields) => {
+                let items = match self.pending.lock().unwrap().get(&fields.invocation_id) {
+                    Some(PendingInvocation::Stream { items, .. }) => Some(items.clone()),
+                    _ => None,
+                };
+                if let Some(items) = items {
+                    // Waits for the stream to be polled once its buffer is full, a dropped stream fails at once.
+                    let _ = items.send(fields.item).await;
+                }
+            }
+            Messsage::Completion(CompletionFields {
+                invocation_id,
+                error,
+                result,
+                ..
+            }) => {
+                match self.remove_pending(&invocation_id) {
+                    Some(pending) => pending.complete(match error {
+                        Some(error) => Err(Error::hub_error(&error)),
+                        None => Ok(result.unwrap_or(Value::Null)),
+                    }),
+                    None => event!(debug, %invocation_id, "completion of an unknown invocation"),
+                }
+            }
+            Messsage::Close(fields) => return Dispatch::Close(fields),
+            _ => {}
+        }
+        Dispatch::Continue
+    }
+
+    fn remove_listener(&self, key: &str, id: u64) {
+        let mut listeners = self.listeners.lock().unwrap();
+        if let Some(handlers) = listeners.get_mut(key) {
+            handlers.retain(|(handler_id, _)| *handler_id != id);
+            if handlers.is_empty() {
+                listeners.remove(key);
+            }
+        }
+    }
+
+    fn report_handler_error(&self, error: Error) {
+        let callback = self.error_handler.lock().unwrap().clone();
+        if let Some(callback) = callback {
+            callback(error);
+        }
+    }
+
+    fn fail_pending(&self, error: &Error) {
+        let pending: Vec<_> = self.pending.lock().unwrap().drain().collect();
+        if !pending.is_empty() {
+            self.observe(|observer| observer.pending_invocations(0));
+        }
+        for (_, invocation) in pending {
+            invocation.complete(Err(error.clone()));
+        }
+    }
+
+    /// Tears down the session `session_id` after its transport went away.
+    ///
+    /// Sessions already taken over by `stop` or a newer transport are ignored.
+    fn connection_lost(self: &Arc<Self>, session_id: u64, reason: CloseReason) {
+        {
+            let mut session = self.session.lock().unwrap();
+            match session.as_ref() {
+                Some(current) if current.id == session_id => {}
+                _ => return,
+            }
+            // Dropping the session closes the outbound queue, the writer then sends a close frame.
+            session.take();
+        }
+
+        let reconnect = reason.allow_reconnect && !self.options.reconnect_delays.is_empty();
+        event!(
+            warn,
+            reason = reason.error.as_deref(),
+            allow_reconnect = reason.allow_reconnect,
+            reconnect,
+            "connection lost"
+        );
+        let next = if reconnect {
+            HubConnectionState::Reconnecting
+        } else {
+            HubConnectionState::Disconnected
+        };
+        if self.transition(next).is_err() {
+            return;
+        }
+        self.fail_pending(&Error::connection_closed_error(reason.error.as_deref()));
+        if reconnect {
+            self.runtime.spawn(Box::pin(self.clone().reconnect()));
+        }
+    }
+}
+
+/// Removes a pending invocation when dropped, a no-op once its completion took it.
+struct PendingSlot<'a> {
+    inner: &'a ConnectionInner,
+    invocation_id: Option<&'a str>,
+}
+
+impl<'a> PendingSlot<'a> {
+    fn new(inner: &'a ConnectionInner, invocation_id: &'a str) -> Self {
+        PendingSlot {
+            inner,
+            invocation_id: Some(invocation_id),
+        }
+    }
+
+    /// Keeps the invocation pending, something else now removes it.
+    fn release(mut self) {
+        self.invocation_id = None;
+    }
+}
+
+impl Drop for PendingSlot<'_> {
+    fn drop(&mut self) {
+        if let Some(invocation_id) = self.invocation_id {
+            self.inner.remove_pending(invocation_id);
+        }
+    }
+}
+
+/// Runs `call` unless `interruption` resolves first.
+async fn interruptible<T, F, I>(call: F, interruption: I) -> Result<T, Error>
+where
+    F: Future<Output = Result<T, Error>>,
+    I: Future<Output = Error> + Unpin,
+{
+    match future::select(pin!(call), interruption).await {
+        Either::Left((result, _)) => result,
+        Either::Right((error, _)) => Err(error),
+    }
+}
+
+/// A stream the server has not completed yet, cancelled on the server when dropped.
+struct StreamInvocation {
+    connection: Weak<ConnectionInner>,
+    invocation_id: String,
+}
+
+impl Drop for StreamInvocation {
+    fn drop(&mut self) {
+        if let Some(inner) = self.connection.upgrade() {
+            inner.cancel_stream(&self.invocation_id);
+        }
+    }
+}
+
+/// Items produced by a streaming invocation, ends when the server completes the stream.
+///
+/// Dropping the stream before it ends cancels the invocation on the server.
+pub struct HubStream {
+    receiver: mpsc::Receiver<Value>,
+    /// The error the stream ends with, if any, taken once the items run out.
+    failure: Option<oneshot::Receiver<Error>>,
+    interruption: Option<BoxFuture<'static, Error>>,
+    invocation: Option<StreamInvocation>,
+}
+
+impl Stream for HubStream {
+    type Item = Result<Value, Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if let Some(interruption) = self.interruption.as_mut() {
+            if let Poll::Ready(error) = interruption.as_mut().poll(cx) {
+                // Ends the stream after the error, whatever the server still sends.
+                self.interruption = None;
+                self.invocation = None;
+                self.failure = None;
+                self.receiver.close();
+                while self.receiver.try_recv().is_ok() {}
+                return Poll::Ready(Some(Err(error)));
+            }
+        }
+        match ready!(self.receiver.poll_recv(cx)) {
+            Some(item) => Poll::Ready(Some(Ok(item))),
+            // The error is sent before the senders are dropped, so it is there once the items run out.
+            None => Poll::Ready(self.failure.take().and_then(|mut failure| failure.try_recv().ok()).map(Err)),
+        }
+    }
+}
+
+/// Messages received by a connection, see [`HubConnection::messages`].
+pub struct MessageStream {
+    messages: Pin<Box<dyn Stream<Item = Messsage> + Send>>,
+}
+
+impl Stream for MessageStream {
+    type Item = Messsage;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.messages.as_mut().poll_next(cx)
+    }
+}
+
+async fn handshake(writer: &mut TransportSink, reader: &mut TransportStream) -> Result<Vec<Messsage>, Error> {
+    writer
+        .send(Frame::Text(protocol::handshake_request()))
+        .await
+        .map_err(|e| Error::handshake_error(&e.to_string()))?;
+
+    loop {
+        match reader.next().await {
+            Some(Ok(Frame::Text(text))) => return protocol::parse_handshake_response(&text),
+            Some(Ok(Frame::Close)) | None => return Err(Error::handshake_error_simple()),
+            Some(Ok(_)) => continue,
+            Some(Err(e)) => return Err(Error::handshake_error(&e.to_string())),
+        }
+    }
+}
+
+async fn write_loop(
+    mut writer: TransportSink,
+    outbound: Arc<OutboundQueue>,
+    runtime: Arc<dyn Runtime>,
+    keep_alive_interval: Duration,
+    log_payloads: bool,
+    observer: Option<Arc<dyn ConnectionObserver>>,
+) {
+    loop {
+        let message = match runtime::timeout(&*runtime, keep_alive_interval, outbound.pop()).await {
+            Ok(Some(message)) => message,
+            Ok(None) => break,
+            Err(_) => Messsage::Ping,
+        };
+        let kind = message.kind();
+        if let Some(text) = message.serialize() {
+            diagnostics::sent(kind, &text, log_payloads);
+            if let Some(observer) = &observer {
+                observer.message_sent(kind, text.len());
+            }
+            if writer.send(Frame::Text(text)).await.is_err() {
+                break;
+            }
+        }
+    }
+    close_transport(&mut writer).await;
+}
+
+/// Sends a close frame with the Normal status before closing, the sink alone would send none.
+async fn close_transport(writer: &mut TransportSink) {
+    let _ = writer.send(Frame::Close).await;
+    let _ = writer.close().await;
+}
+
+async fn read_loop(
+    mut reader: TransportStream,
+    inbound: mpsc::Sender<Inbound>,
+    runtime: Arc<dyn Runtime>,
+    options: ConnectionOptions,
+    buffered: Vec<Messsage>,
+) {
+    let mut buffered = buffered.into_iter();
+    let reason = loop {
+        // Waiting for room in the inbound queue holds back the transport behind a slow handler.
+        for message in buffered.by_ref() {
+            if inbound.send(Inbound::Message(message)).await.is_err() {
+                return;
+            }
+        }
+
+        let frame = match runtime::timeout(&*runtime, options.server_timeout, reader.next()).await {
+            Err(_) => break CloseReason::transport("server timeout elapsed without receiving a message"),
+            Ok(None) | Ok(Some(Ok(Frame::Close))) => break CloseReason::transport("transport closed"),
+            Ok(Some(Err(e))) => break CloseReason::transport(&e.to_string()),
+            Ok(Some(Ok(frame))) => frame,
+        };
+        if let Frame::Text(text) = frame {
+            buffered = protocol::split_records(&text)
+                .filter_map(|record| {
+                    let message = Messsage::deserialize(record);
+                    diagnostics::received(message.as_ref(), record, options.log_payloads);
+                    if let (Some(observer), Some(message)) = (&options.observer, &message) {
+                        // The record separator is on the wire too.
+                        observer.message_received(message.kind(), record.len() + 1);
+                    }
+                    message
+                })
+                .collect::<Vec<_>>()
+                .into_iter();
+        }
+    };
+    let _ = inbound.send(Inbound::Lost(reason)).await;
+}
+
+/// Dispatches what the reader of session `session_id` received, in order.
+async fn dispatch_loop(mut inbound: mpsc::Receiver<Inbound>, connection: Weak<ConnectionInner>, session_id: u64) {
+    while let Some(received) = inbound.recv().await {
+        let inner = match connection.upgrade() {
+            Some(inner) => inner,
+            None => return,
+        };
+        let reason = match received {
+            Inbound::Message(message) => {
+                if inner.received.receiver_count() > 0 {
+                    let _ = inner.received.send(message.clone());
+                }
+                match inner.dispatch(message).await {
+                    Dispatch::Close(fields) => CloseReason {
+                        error: fields.error,
+                        allow_reconnect: fields.allow_reconnect,
+                    },
+                    Dispatch::Continue => continue,
+                }
+            }
+            Inbound::Lost(reason) => reason,
+        };
+        inner.connection_lost(session_id, reason);
+        return;
+    }
+}
@@ -0,0 +1,29 @@
+use std::time::Duration;
+
+use crate::error::Error;
+
+/// Receives the measurements of a connection, to feed a metrics backend.
+///
+/// Every method defaults to doing nothing, implement the ones of interest and pass the
+/// observer to [`HubConnectionBuilder::with_observer`](crate::HubConnectionBuilder::with_observer).
+/// Methods are called from the connection's own tasks and must not block. A connection
+/// without an observer skips the measurements altogether.
+pub trait ConnectionObserver: Send + Sync + 'static {
+    /// A message of type `kind` was written to the transport, `bytes` long.
+    fn message_sent(&self, _kind: &'static str, _bytes: usize) {}
+
+    /// A message of type `kind` was read from the transport, `bytes` long.
+    fn message_received(&self, _kind: &'static str, _bytes: usize) {}
+
+    /// An `invoke` of `target` completed after `latency`, with a result or an error.
+    fn invocation_completed(&self, _target: &str, _latency: Duration, _succeeded: bool) {}
+
+    /// The number of invocations and streams waiting for the server changed.
+    fn pending_invocations(&self, _count: usize) {}
+
+    /// Reconnect attempt `attempt`, counted from 1, finished.
+    fn reconnect_attempt(&self, _attempt: usize, _succeeded: bool) {}
+
+    /// The server rejected the handshake or did not answer it in time.
+    fn handshake_failed(&self, _error: &Error) {}
+}
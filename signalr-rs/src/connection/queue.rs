@@ -0,0 +1,174 @@
+use std::{
+    collections::VecDeque,
+    pin::pin,
+    sync::{Arc, Mutex},
+};
+
+use tokio::sync::Notify;
+
+use crate::{error::Error, protocol::responses::Messsage};
+
+/// What `send`, `invoke` and `stream` do when the outbound queue is full.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum OutboundPolicy {
+    /// Wait until the transport catches up.
+    #[default]
+    Wait,
+    /// Fail with a `QueueFull` error.
+    FailFast,
+    /// Drop the oldest message queued by [`send`](super::HubConnection::send) to make room,
+    /// waiting like `Wait` when only invocations expecting a result are queued.
+    DropOldest,
+}
+
+struct State {
+    messages: VecDeque<Messsage>,
+    closed: bool,
+}
+
+/// Messages waiting for the writer of a session, bounded by `capacity`.
+pub(crate) struct OutboundQueue {
+    capacity: usize,
+    policy: OutboundPolicy,
+    state: Mutex<State>,
+    /// Woken when a message is queued or the queue is closed, only the writer waits on it.
+    readable: Notify,
+    /// Woken when a message leaves the queue or the queue is closed.
+    writable: Notify,
+}
+
+impl OutboundQueue {
+    pub(crate) fn new(capacity: usize, policy: OutboundPolicy) -> Self {
+        OutboundQueue {
+            capacity: capacity.max(1),
+            policy,
+            state: Mutex::new(State {
+                messages: VecDeque::new(),
+                closed: false,
+            }),
+            readable: Notify::new(),
+            writable: Notify::new(),
+        }
+    }
+
+    /// Queues `message` according to the policy, failing once the queue is closed.
+    pub(crate) async fn push(&self, message: Messsage) -> Result<(), Error> {
+        loop {
+            let mut writable = pin!(self.writable.notified());
+            {
+                let mut state = self.state.lock().unwrap();
+                if state.closed {
+                    return Err(Error::connection_closed_error(None));
+                }
+                if state.messages.len() >= self.capacity {
+                    match self.policy {
+                        OutboundPolicy::Wait => {}
+                        OutboundPolicy::FailFast => return Err(Error::queue_full_error(self.capacity)),
+                        OutboundPolicy::DropOldest => {
+                            if let Some(oldest) = state.messages.iter().position(is_send) {
+                                state.messages.remove(oldest);
+                            }
+                        }
+                    }
+                }
+                if state.messages.len() < self.capacity {
+                    state.messages.push_back(message);
+                    self.readable.notify_one();
+                    return Ok(());
+                }
+                // Registered before the lock is released so a concurrent pop or close is not missed.
+                writable.as_mut().enable();
+            }
+            writable.await;
+        }
+    }
+
+    /// Queues `message` past the capacity, for the Close ending a session and stream cancellations.
+    pub(crate) fn push_unbounded(&self, message: Messsage) {
+        let mut state = self.state.lock().unwrap();
+        if !state.closed {
+            state.messages.push_back(message);
+            self.readable.notify_one();
+        }
+    }
+
+    /// Next message to write, `None` once the queue is closed and drained.
+    pub(crate) async fn pop(&self) -> Option<Messsage> {
+        loop {
+            let readable = self.readable.notified();
+            {
+                let mut state = self.state.lock().unwrap();
+                if let Some(message) = state.messages.pop_front() {
+                    self.writable.notify_one();
+                    return Some(message);
+                }
+                if state.closed {
+                    return None;
+                }
+            }
+            // `notify_one` keeps a permit when the writer is not waiting yet.
+            readable.await;
+        }
+    }
+
+    /// Refuses new messages, the writer still drains the queued ones.
+    pub(crate) fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.readable.notify_one();
+        self.writable.notify_waiters();
+    }
+}
+
+/// A session's hold on its queue, closing it when dropped so the writer finishes.
+pub(crate) struct Outbound(Arc<OutboundQueue>);
+
+impl Outbound {
+    pub(crate) fn new(queue: Arc<OutboundQueue>) -> Self {
+        Outbound(queue)
+    }
+
+    pub(crate) fn queue(&self) -> Arc<OutboundQueue> {
+        self.0.clone()
+    }
+}
+
+impl Drop for Outbound {
+    fn drop(&mut self) {
+        self.0.close();
+    }
+}
+
+/// Whether `message` was queued by `send`, without a completion anybody waits for.
+fn is_send(message: &Messsage) -> bool {
+    matches!(message, Messsage::Invocation(fields) if fields.invocation_id().is_none())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{OutboundPolicy, OutboundQueue};
+    use crate::{
+        error::ErrorKind,
+        protocol::responses::{InvocationFields, Messsage},
+    };
+
+    fn send(target: &str) -> Messsage {
+        Messsage::Invocation(InvocationFields::new(None, target, vec![]))
+    }
+
+    #[tokio::test]
+    async fn applies_the_policy_when_full() {
+        let queue = OutboundQueue::new(2, OutboundPolicy::FailFast);
+        queue.push(send("1")).await.unwrap();
+        queue.push(send("2")).await.unwrap();
+        assert_eq!(queue.push(send("3")).await.unwrap_err().kind(), ErrorKind::QueueFull);
+
+        let queue = OutboundQueue::new(2, OutboundPolicy::DropOldest);
+        queue.push(Messsage::Invocation(InvocationFields::new(Some("0".to_owned()), "invoke", vec![]))).await.unwrap();
+        queue.push(send("1")).await.unwrap();
+        queue.push(send("2")).await.unwrap();
+        queue.close();
+        assert!(matches!(queue.pop().await, Some(Messsage::Invocation(fields)) if fields.target() == "invoke"));
+        assert_eq!(queue.pop().await, Some(send("2")));
+        assert_eq!(queue.pop().await, None);
+    }
+}
@@ -0,0 +1,44 @@
+use std::sync::Arc;
+
+use serde_json::Value;
+
+use crate::{error::Error, runtime::BoxFuture};
+
+type Dispatch = dyn Fn(Vec<Value>) -> Result<BoxFuture<'static, ()>, Error> + Send + Sync;
+
+/// A set of handlers for server-to-client methods, registered at once with
+/// [`HubConnection::register`](super::HubConnection::register).
+///
+/// Implemented by `#[hub_receiver]` on the `impl` of a receiver trait.
+pub trait HubReceiver: Send + Sync + 'static {
+    fn handlers(self: Arc<Self>) -> Vec<ReceiverHandler>;
+}
+
+/// One hub method of a [`HubReceiver`].
+pub struct ReceiverHandler {
+    target: &'static str,
+    dispatch: Box<Dispatch>,
+}
+
+impl ReceiverHandler {
+    /// `dispatch` checks and deserializes the arguments, returning the call to run.
+    pub fn new<F>(target: &'static str, dispatch: F) -> Self
+    where
+        F: Fn(Vec<Value>) -> Result<BoxFuture<'static, ()>, Error> + Send + Sync + 'static,
+    {
+        ReceiverHandler {
+            target,
+            dispatch: Box::new(dispatch),
+        }
+    }
+
+    /// Name of the hub method this handler answers to.
+    pub fn target(&self) -> &'static str {
+        self.target
+    }
+
+    /// Prepares a call with `arguments`, failing if they do not match the method's parameters.
+    pub fn call(&self, arguments: Vec<Value>) -> Result<BoxFuture<'static, ()>, Error> {
+        (self.dispatch)(arguments)
+    }
+}
@@ -0,0 +1,60 @@
+use std::fmt;
+
+/// Lifecycle of a [`HubConnection`](super::HubConnection).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub enum HubConnectionState {
+    #[default]
+    Disconnected,
+    Connecting,
+    Connected,
+    Reconnecting,
+    Disconnecting,
+}
+
+impl HubConnectionState {
+    /// Whether the connection is allowed to move from `self` to `next`.
+    pub fn can_transition_to(self, next: HubConnectionState) -> bool {
+        use HubConnectionState::*;
+        matches!(
+            (self, next),
+            (Disconnected, Connecting)
+                | (Connecting, Connected)
+                | (Connecting, Disconnecting)
+                | (Connecting, Disconnected)
+                | (Connected, Reconnecting)
+                | (Connected, Disconnecting)
+                | (Connected, Disconnected)
+                | (Reconnecting, Connected)
+                | (Reconnecting, Disconnecting)
+                | (Reconnecting, Disconnected)
+                | (Disconnecting, Disconnected)
+        )
+    }
+}
+
+impl fmt::Display for HubConnectionState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            HubConnectionState::Disconnected => "Disconnected",
+            HubConnectionState::Connecting => "Connecting",
+            HubConnectionState::Connected => "Connected",
+            HubConnectionState::Reconnecting => "Reconnecting",
+            HubConnectionState::Disconnecting => "Disconnecting",
+        };
+        f.write_str(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::HubConnectionState::*;
+
+    #[test]
+    fn only_disconnected_can_start_connecting() {
+        assert!(Disconnected.can_transition_to(Connecting));
+        assert!(!Connected.can_transition_to(Connecting));
+        assert!(!Reconnecting.can_transition_to(Connecting));
+        assert!(!Disconnecting.can_transition_to(Connected));
+        assert!(Disconnecting.can_transition_to(Disconnected));
+    }
+}
@@ -0,0 +1,38 @@
+use std::sync::Weak;
+
+use super::ConnectionInner;
+
+/// A handler registered with [`on`](super::HubConnection::on) or one of its variants, which
+/// stays registered as long as the subscription is kept.
+#[must_use = "the handler is unregistered when the subscription is dropped, call `detach` to keep it"]
+pub struct Subscription {
+    connection: Option<Weak<ConnectionInner>>,
+    target: String,
+    id: u64,
+}
+
+impl Subscription {
+    pub(super) fn new(connection: Weak<ConnectionInner>, target: String, id: u64) -> Self {
+        Subscription {
+            connection: Some(connection),
+            target,
+            id,
+        }
+    }
+
+    /// Unregisters the handler now, like dropping the subscription.
+    pub fn off(self) {}
+
+    /// Keeps the handler registered for as long as the connection lives.
+    pub fn detach(mut self) {
+        self.connection = None;
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        if let Some(inner) = self.connection.take().and_then(|connection| connection.upgrade()) {
+            inner.remove_listener(&self.target, self.id);
+        }
+    }
+}
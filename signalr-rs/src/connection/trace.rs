@@ -0,0 +1,99 @@
+//! Spans and trace context propagation of hub invocations, no-ops without the `tracing` feature.
+
+use std::future::Future;
+
+use crate::{protocol::responses::Headers, runtime::BoxFuture};
+
+#[cfg(feature = "tracing")]
+use crate::trace_context::{CurrentGuard, TraceContext};
+#[cfg(feature = "tracing")]
+use tracing::{span::EnteredSpan, Instrument, Span};
+
+/// The span of an outgoing invocation.
+#[cfg(feature = "tracing")]
+pub(crate) type OutboundSpan = Span;
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct OutboundSpan;
+
+/// Starts the span of an outgoing `kind` call of `target` and injects its context into `headers`.
+///
+/// A `traceparent` already in `headers` is taken as the parent, otherwise the context of the
+/// running handler, otherwise the call starts a new trace.
+#[cfg(feature = "tracing")]
+pub(crate) fn outbound(kind: &'static str, target: &str, headers: &mut Headers) -> OutboundSpan {
+    let parent = TraceContext::extract(headers).or_else(TraceContext::current);
+    let context = parent.as_ref().map_or_else(TraceContext::new_root, TraceContext::child);
+    context.inject(headers);
+    tracing::info_span!(
+        "hub_invocation",
+        kind,
+        target,
+        trace_id = %context.trace_id(),
+        span_id = %context.span_id(),
+        parent_span_id = parent.as_ref().map(TraceContext::span_id).as_deref(),
+    )
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn outbound(_kind: &'static str, _target: &str, _headers: &mut Headers) -> OutboundSpan {
+    OutboundSpan
+}
+
+#[cfg(feature = "tracing")]
+pub(crate) async fn instrument<F: Future>(call: F, span: OutboundSpan) -> F::Output {
+    call.instrument(span).await
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) async fn instrument<F: Future>(call: F, _span: OutboundSpan) -> F::Output {
+    call.await
+}
+
+/// Keeps the span and trace context of a handler current while it runs.
+#[cfg(feature = "tracing")]
+pub(crate) struct HandlerScope {
+    // Declared first so the context is restored before the span is exited.
+    _current: CurrentGuard,
+    _span: EnteredSpan,
+}
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct HandlerScope;
+
+/// Enters the span of a handler of `target`, a child of the context carried by `headers`.
+#[cfg(feature = "tracing")]
+pub(crate) fn inbound(target: &str, headers: &Headers) -> HandlerScope {
+    let parent = TraceContext::extract(headers);
+    let context = parent.as_ref().map_or_else(TraceContext::new_root, TraceContext::child);
+    let span = tracing::info_span!(
+        "hub_handler",
+        target,
+        trace_id = %context.trace_id(),
+        span_id = %context.span_id(),
+        parent_span_id = parent.as_ref().map(TraceContext::span_id).as_deref(),
+    );
+    HandlerScope {
+        _span: span.entered(),
+        _current: context.enter(),
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn inbound(_target: &str, _headers: &Headers) -> HandlerScope {
+    HandlerScope
+}
+
+/// Carries the span and trace context of the running handler into a task it spawns.
+#[cfg(feature = "tracing")]
+pub(crate) fn bind(task: BoxFuture<'static, ()>) -> BoxFuture<'static, ()> {
+    let context = TraceContext::current();
+    let mut task = Box::pin(task.instrument(Span::current()));
+    Box::pin(futures_util::future::poll_fn(move |cx| {
+        let _current = context.clone().map(TraceContext::enter);
+        task.as_mut().poll(cx)
+    }))
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn bind(task: BoxFuture<'static, ()>) -> BoxFuture<'static, ()> {
+    task
+}
@@ -0,0 +1,105 @@
+//! `tracing` events of the connection, compiled out without the `tracing` feature.
+
+use crate::protocol::responses::Messsage;
+
+/// Emits a `tracing` event at `level`, taking the arguments of the `tracing` macros.
+#[cfg(feature = "tracing")]
+macro_rules! event {
+    ($level:ident, $($arguments:tt)*) => {
+        ::tracing::$level!($($arguments)*)
+    };
+}
+
+/// Without `tracing`, only borrows the fields in a branch that never runs so the values they
+/// name still count as used.
+#[cfg(not(feature = "tracing"))]
+macro_rules! event {
+    (@use) => {
+        ()
+    };
+    (@use $message:literal $(, $format:expr)* $(,)?) => {{
+        $(if false {
+            let _ = &$format;
+        })*
+    }};
+    (@use $name:ident = %$value:expr $(, $($rest:tt)*)?) => {{
+        if false {
+            let _ = &$value;
+        }
+        $crate::diagnostics::event!(@use $($($rest)*)?)
+    }};
+    (@use $name:ident = ?$value:expr $(, $($rest:tt)*)?) => {{
+        if false {
+            let _ = &$value;
+        }
+        $crate::diagnostics::event!(@use $($($rest)*)?)
+    }};
+    (@use $name:ident = $value:expr $(, $($rest:tt)*)?) => {{
+        if false {
+            let _ = &$value;
+        }
+        $crate::diagnostics::event!(@use $($($rest)*)?)
+    }};
+    (@use %$value:expr $(, $($rest:tt)*)?) => {{
+        if false {
+            let _ = &$value;
+        }
+        $crate::diagnostics::event!(@use $($($rest)*)?)
+    }};
+    (@use ?$value:expr $(, $($rest:tt)*)?) => {{
+        if false {
+            let _ = &$value;
+        }
+        $crate::diagnostics::event!(@use $($($rest)*)?)
+    }};
+    (@use $value:ident $(, $($rest:tt)*)?) => {{
+        if false {
+            let _ = &$value;
+        }
+        $crate::diagnostics::event!(@use $($($rest)*)?)
+    }};
+    ($level:ident, $($arguments:tt)*) => {
+        $crate::diagnostics::event!(@use $($arguments)*)
+    };
+}
+
+pub(crate) use event;
+
+/// Reports a `kind` message written to the transport, `record` only when payloads are logged.
+#[cfg(feature = "tracing")]
+pub(crate) fn sent(kind: &'static str, record: &str, log_payloads: bool) {
+    tracing::trace!(
+        kind,
+        payload = log_payloads.then_some(record.trim_end_matches('\u{1e}')),
+        "sent message"
+    );
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn sent(_kind: &'static str, _record: &str, _log_payloads: bool) {}
+
+/// Reports a record read from the transport, `message` being `None` when it did not parse.
+#[cfg(feature = "tracing")]
+pub(crate) fn received(message: Option<&Messsage>, record: &str, log_payloads: bool) {
+    let payload = log_payloads.then_some(record);
+    match message {
+        Some(message) => tracing::trace!(kind = message.kind(), payload, "received message"),
+        None => tracing::warn!(payload, "dropped a message that does not parse"),
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn received(_message: Option<&Messsage>, _record: &str, _log_payloads: bool) {}
+
+/// Reports an invocation of `target` without a handler, as a warning when `warn` is set.
+#[cfg(feature = "tracing")]
+pub(crate) fn unhandled(target: &str, warn: bool) {
+    if warn {
+        tracing::warn!(target, "no handler for the invocation");
+    } else {
+        tracing::debug!(target, "no handler for the invocation");
+    }
+}
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn unhandled(_target: &str, _warn: bool) {}
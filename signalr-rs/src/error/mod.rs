@@ -1,17 +1,161 @@
+use std::{fmt, time::Duration};
+
+use crate::connection::HubConnectionState;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The builder was given an invalid configuration.
+    Configuration,
+    /// The `/negotiate` request failed or returned an unexpected payload.
+    Negotiation,
+    /// The underlying transport failed or was lost.
+    Transport,
+    /// The server rejected or did not answer the handshake.
+    Handshake,
+    /// The operation is not allowed in the current connection state.
+    InvalidState,
+    /// A message could not be encoded or decoded.
+    Protocol,
+    /// The server completed an invocation with an error.
+    Hub,
+    /// The connection was closed while the operation was in flight.
+    ConnectionClosed,
+    /// A server invocation carried arguments its handler cannot accept.
+    InvalidArguments,
+    /// The outbound queue is full and its policy is to fail fast.
+    QueueFull,
+    /// The call did not finish before its timeout.
+    Timeout,
+    /// The call was cancelled through its cancellation token.
+    Cancelled,
+    /// A hub already has a client connected with the same connection id.
+    DuplicateConnection,
+}
+
+#[derive(Clone, Debug)]
 pub struct Error {
-    message: String,   
+    kind: ErrorKind,
+    message: String,
 }
 
 impl Error {
     pub fn handshake_error(inner: &str) -> Self {
         Error {
+            kind: ErrorKind::Handshake,
             message: format!("Handshake failed, inner {}", inner),
         }
     }
 
     pub fn handshake_error_simple() -> Self {
         Error {
-            message: format!("Handshake failed")
+            kind: ErrorKind::Handshake,
+            message: "Handshake failed".to_owned(),
+        }
+    }
+
+    pub fn configuration_error(inner: &str) -> Self {
+        Error {
+            kind: ErrorKind::Configuration,
+            message: format!("Invalid configuration, inner {}", inner),
+        }
+    }
+
+    pub fn negotiation_error(inner: &str) -> Self {
+        Error {
+            kind: ErrorKind::Negotiation,
+            message: format!("Negotiation failed, inner {}", inner),
+        }
+    }
+
+    pub fn transport_error(inner: &str) -> Self {
+        Error {
+            kind: ErrorKind::Transport,
+            message: format!("Transport failed, inner {}", inner),
+        }
+    }
+
+    pub fn invalid_state_error(state: HubConnectionState) -> Self {
+        Error {
+            kind: ErrorKind::InvalidState,
+            message: format!("Operation not allowed while the connection is {}", state),
+        }
+    }
+
+    pub fn protocol_error(inner: &str) -> Self {
+        Error {
+            kind: ErrorKind::Protocol,
+            message: format!("Protocol error, inner {}", inner),
+        }
+    }
+
+    pub fn hub_error(inner: &str) -> Self {
+        Error {
+            kind: ErrorKind::Hub,
+            message: inner.to_owned(),
+        }
+    }
+
+    pub fn connection_closed_error(reason: Option<&str>) -> Self {
+        let message = match reason {
+            Some(reason) => format!("Connection closed, inner {}", reason),
+            None => "Connection closed".to_owned(),
+        };
+        Error {
+            kind: ErrorKind::ConnectionClosed,
+            message,
+        }
+    }
+
+    pub fn invalid_arguments_error(target: &str, inner: &str) -> Self {
+        Error {
+            kind: ErrorKind::InvalidArguments,
+            message: format!("Invalid arguments for {}, inner {}", target, inner),
+        }
+    }
+
+    pub fn queue_full_error(capacity: usize) -> Self {
+        Error {
+            kind: ErrorKind::QueueFull,
+            message: format!("Outbound queue full, {} messages waiting to be sent", capacity),
+        }
+    }
+
+    pub fn timeout_error(timeout: Duration) -> Self {
+        Error {
+            kind: ErrorKind::Timeout,
+            message: format!("Call timed out after {:?}", timeout),
         }
     }
-}
\ No newline at end of file
+
+    pub fn cancelled_error() -> Self {
+        Error {
+            kind: ErrorKind::Cancelled,
+            message: "Call cancelled".to_owned(),
+        }
+    }
+
+    pub fn duplicate_connection_error(connection_id: &str) -> Self {
+        Error {
+            kind: ErrorKind::DuplicateConnection,
+            message: format!("Connection {} is already connected", connection_id),
+        }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Error {}
@@ -1,21 +1,81 @@
 
-use std::{collections::HashMap, time::Duration};
-use reqwest::Client;
+use std::{sync::Arc, time::Duration};
 
 pub mod protocol;
+mod diagnostics;
 pub mod error;
+pub mod connection;
+pub mod runtime;
+#[cfg(feature = "blocking")]
+pub mod blocking;
+#[cfg(feature = "server")]
+pub mod server;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "tracing")]
+pub mod trace_context;
+#[doc(hidden)]
+pub mod macro_support;
+
+pub use connection::{
+    CancellationToken, ConnectionObserver, DispatchMode, HubCallContext, HubConnection, HubConnectionState, HubReceiver,
+    HubStream, InvocationOptions, MessageStream, OutboundPolicy, ReceiverHandler, Subscription,
+};
+pub use error::{Error, Result};
+pub use protocol::{
+    arguments::{FromHubArgs, IntoHubArgs},
+    responses::Headers,
+};
+/// Re-exported so typed stream proxies can name the trait without depending on `futures-util`.
+pub use futures_util::Stream;
+#[cfg(feature = "tracing")]
+pub use trace_context::TraceContext;
+#[cfg(feature = "macros")]
+pub use signalr_rs_macro::{hub_client, hub_receiver};
+
+const DEFAULT_KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+const DEFAULT_SERVER_TIMEOUT: Duration = Duration::from_secs(30);
+const DEFAULT_OUTBOUND_CAPACITY: usize = 1024;
+const DEFAULT_INBOUND_CAPACITY: usize = 1024;
 
-#[derive(Default)]
 pub struct HubConnectionBuilder {
     hub_url: String,
+    keep_alive_interval: Duration,
+    server_timeout: Duration,
+    reconnect_delays: Vec<Duration>,
+    log_payloads: bool,
+    observer: Option<Arc<dyn connection::ConnectionObserver>>,
+    outbound_capacity: usize,
+    outbound_policy: OutboundPolicy,
+    inbound_capacity: usize,
+    dispatch_mode: DispatchMode,
+    warn_unhandled: bool,
+    runtime: Option<Arc<dyn runtime::Runtime>>,
+    transport: Option<Arc<dyn runtime::Transport>>,
 }
 
+#[deprecated(note = "not used by the crate, build connections with `HubConnectionBuilder`")]
 pub struct BaseHubConnectionBuilder;
-pub struct HubConnection {
-    listeners: HashMap<String, Box<dyn Executable>>
-}
 
-trait Executable {}
+impl Default for HubConnectionBuilder {
+    fn default() -> Self {
+        HubConnectionBuilder {
+            hub_url: String::new(),
+            keep_alive_interval: DEFAULT_KEEP_ALIVE_INTERVAL,
+            server_timeout: DEFAULT_SERVER_TIMEOUT,
+            reconnect_delays: Vec::new(),
+            log_payloads: false,
+            observer: None,
+            outbound_capacity: DEFAULT_OUTBOUND_CAPACITY,
+            outbound_policy: OutboundPolicy::Wait,
+            inbound_capacity: DEFAULT_INBOUND_CAPACITY,
+            dispatch_mode: DispatchMode::Sequential,
+            warn_unhandled: true,
+            runtime: None,
+            transport: None,
+        }
+    }
+}
 
 impl HubConnectionBuilder {
     pub fn new() -> HubConnectionBuilder {
@@ -25,16 +85,98 @@ impl HubConnectionBuilder {
     }
 
     pub fn with_url(self, hub_url: String) -> HubConnectionBuilder {
-        HubConnectionBuilder { hub_url }
+        HubConnectionBuilder { hub_url, ..self }
+    }
+
+    /// How long the client waits without sending anything before it sends a ping.
+    pub fn with_keep_alive_interval(self, keep_alive_interval: Duration) -> HubConnectionBuilder {
+        HubConnectionBuilder { keep_alive_interval, ..self }
+    }
+
+    /// How long the client waits without receiving anything before it considers the server gone.
+    pub fn with_server_timeout(self, server_timeout: Duration) -> HubConnectionBuilder {
+        HubConnectionBuilder { server_timeout, ..self }
+    }
+
+    /// Reconnects automatically after the transport is lost, waiting each delay in turn before
+    /// another attempt. The connection is closed once every delay has been used.
+    pub fn with_automatic_reconnect(self, reconnect_delays: Vec<Duration>) -> HubConnectionBuilder {
+        HubConnectionBuilder { reconnect_delays, ..self }
+    }
+
+    /// Includes the JSON of every sent and received message in the `tracing` events, which
+    /// otherwise only name the message type. Off by default, arguments may be sensitive.
+    pub fn with_payload_logging(self, log_payloads: bool) -> HubConnectionBuilder {
+        HubConnectionBuilder { log_payloads, ..self }
     }
 
-    pub async fn build(&self) -> Option<protocol::responses::NegotiateRequest>  {
+    /// Reports the connection's measurements to `observer`, see [`ConnectionObserver`].
+    pub fn with_observer<O: ConnectionObserver>(self, observer: O) -> HubConnectionBuilder {
+        HubConnectionBuilder { observer: Some(Arc::new(observer)), ..self }
+    }
+
+    /// Bounds the messages waiting to be written to `capacity`, 1024 by default, and chooses
+    /// what sending does when they are that many.
+    pub fn with_outbound_queue(self, outbound_capacity: usize, outbound_policy: OutboundPolicy) -> HubConnectionBuilder {
+        HubConnectionBuilder { outbound_capacity, outbound_policy, ..self }
+    }
+
+    /// Bounds the received messages waiting to be dispatched, and the handler calls waiting to
+    /// finish, to `capacity` each, 1024 by default. Once they are that many the connection stops
+    /// reading from the transport.
+    pub fn with_inbound_capacity(self, inbound_capacity: usize) -> HubConnectionBuilder {
+        HubConnectionBuilder { inbound_capacity, ..self }
+    }
+
+    /// How the handlers of server invocations run relative to each other, one at a time by default.
+    pub fn with_dispatch_mode(self, dispatch_mode: DispatchMode) -> HubConnectionBuilder {
+        HubConnectionBuilder { dispatch_mode, ..self }
+    }
+
+    /// Whether invocations of targets without a handler are logged as warnings, as they are by
+    /// default, or only at debug level. See also [`HubConnection::on_unhandled`].
+    pub fn with_unhandled_warnings(self, warn_unhandled: bool) -> HubConnectionBuilder {
+        HubConnectionBuilder { warn_unhandled, ..self }
+    }
+
+    /// Runs the connection on `runtime` instead of the one selected by the enabled features,
+    /// tokio when both `tokio` and `async-std` are enabled.
+    pub fn with_runtime<R: runtime::Runtime>(self, runtime: R) -> HubConnectionBuilder {
+        HubConnectionBuilder { runtime: Some(Arc::new(runtime)), ..self }
+    }
+
+    /// Connects through `transport` instead of the one selected by the enabled features.
+    pub fn with_transport<T: runtime::Transport>(self, transport: T) -> HubConnectionBuilder {
+        HubConnectionBuilder { transport: Some(Arc::new(transport)), ..self }
+    }
+
+    /// Builds a `Disconnected` connection, call [`HubConnection::start`] to connect.
+    pub fn build(self) -> Result<HubConnection> {
         if self.hub_url.is_empty() {
-            return None;
+            return Err(error::Error::configuration_error("hub url is empty"));
         }
-        let builder = Client::builder().build();
-        
-        let client = builder.unwrap();
-        protocol::start_negotiation(&client, &self.hub_url).await
+        let runtime = self.runtime
+            .or_else(runtime::default_runtime)
+            .ok_or_else(|| error::Error::configuration_error("no runtime, enable the `tokio` or `async-std` feature or call with_runtime"))?;
+        let transport = match self.transport {
+            Some(transport) => transport,
+            None => runtime::default_transport()
+                .ok_or_else(|| error::Error::configuration_error("no transport, enable the `tokio` or `async-std` feature or call with_transport"))??,
+        };
+
+        let options = connection::ConnectionOptions {
+            hub_url: self.hub_url,
+            keep_alive_interval: self.keep_alive_interval,
+            server_timeout: self.server_timeout,
+            reconnect_delays: self.reconnect_delays,
+            log_payloads: self.log_payloads,
+            observer: self.observer,
+            outbound_capacity: self.outbound_capacity,
+            outbound_policy: self.outbound_policy,
+            inbound_capacity: self.inbound_capacity,
+            dispatch_mode: self.dispatch_mode,
+            warn_unhandled: self.warn_unhandled,
+        };
+        Ok(HubConnection::new(options, runtime, transport))
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,51 @@
+//! Runtime support for the code generated by `signalr-rs-macro`, not a public API.
+
+use futures_util::{
+    future::{self, Either},
+    stream, Stream, StreamExt,
+};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+use crate::{error::Error, HubConnection};
+
+pub fn from_result<T: DeserializeOwned>(value: Value) -> Result<T, Error> {
+    serde_json::from_value(value).map_err(|e| Error::protocol_error(&e.to_string()))
+}
+
+/// Starts the stream lazily, on first poll, and deserializes every item.
+pub fn stream_results<T>(
+    connection: &HubConnection,
+    target: &'static str,
+    arguments: Result<Vec<Value>, Error>,
+) -> impl Stream<Item = Result<T, Error>> + Send + 'static
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    let connection = connection.clone();
+    stream::once(async move {
+        let started = match arguments {
+            Ok(arguments) => connection.stream(target, arguments).await,
+            Err(error) => Err(error),
+        };
+        match started {
+            Ok(items) => Either::Left(items.map(|item| item.and_then(from_result))),
+            Err(error) => Either::Right(stream::once(future::ready(Err(error)))),
+        }
+    })
+    .flatten()
+}
+
+/// Like [`stream_results`] but ends quietly on the first error.
+pub fn stream_items<T>(
+    connection: &HubConnection,
+    target: &'static str,
+    arguments: Result<Vec<Value>, Error>,
+) -> impl Stream<Item = T> + Send + 'static
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    stream_results(connection, target, arguments)
+        .take_while(|item| future::ready(item.is_ok()))
+        .filter_map(|item| future::ready(item.ok()))
+}
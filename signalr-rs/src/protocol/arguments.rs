@@ -0,0 +1,125 @@
+//! Conversions between Rust values and the positional arguments of hub messages.
+//!
+//! Arguments travel as a list of [`Value`]s built through serde, the hub protocol then encodes
+//! that list. Only the JSON hub protocol is implemented so far; MessagePack, when it comes,
+//! encodes the same list and leaves these conversions as they are.
+
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use crate::error::Error;
+
+/// Values that can be sent as the arguments of an invocation.
+///
+/// Implemented for `()`, tuples of up to 16 [`Serialize`] values and `Vec<Value>`, which is
+/// passed through untouched.
+pub trait IntoHubArgs {
+    fn into_hub_args(self) -> Result<Vec<Value>, Error>;
+}
+
+/// Values that can be built from the arguments of a server invocation.
+///
+/// Implemented for `()`, tuples of up to 16 [`DeserializeOwned`] values, which require the exact
+/// number of arguments, and `Vec<Value>`, which accepts any.
+pub trait FromHubArgs: Sized {
+    fn from_hub_args(target: &str, arguments: Vec<Value>) -> Result<Self, Error>;
+}
+
+impl IntoHubArgs for Vec<Value> {
+    fn into_hub_args(self) -> Result<Vec<Value>, Error> {
+        Ok(self)
+    }
+}
+
+impl FromHubArgs for Vec<Value> {
+    fn from_hub_args(_target: &str, arguments: Vec<Value>) -> Result<Self, Error> {
+        Ok(arguments)
+    }
+}
+
+fn to_argument<T: Serialize>(value: T) -> Result<Value, Error> {
+    serde_json::to_value(value).map_err(|e| Error::protocol_error(&e.to_string()))
+}
+
+fn from_argument<T: DeserializeOwned>(target: &str, value: Value) -> Result<T, Error> {
+    serde_json::from_value(value).map_err(|e| Error::invalid_arguments_error(target, &e.to_string()))
+}
+
+fn check_count(target: &str, arguments: &[Value], expected: usize) -> Result<(), Error> {
+    if arguments.len() != expected {
+        let inner = format!("expected {} arguments, got {}", expected, arguments.len());
+        return Err(Error::invalid_arguments_error(target, &inner));
+    }
+    Ok(())
+}
+
+macro_rules! count {
+    () => { 0 };
+    ($head:ident $($tail:ident)*) => { 1 + count!($($tail)*) };
+}
+
+macro_rules! tuple_arguments {
+    ($($name:ident),*) => {
+        impl<$($name: Serialize),*> IntoHubArgs for ($($name,)*) {
+            #[allow(non_snake_case)]
+            fn into_hub_args(self) -> Result<Vec<Value>, Error> {
+                let ($($name,)*) = self;
+                Ok(vec![$(to_argument($name)?),*])
+            }
+        }
+
+        impl<$($name: DeserializeOwned),*> FromHubArgs for ($($name,)*) {
+            #[allow(non_snake_case, unused_variables, unused_mut)]
+            fn from_hub_args(target: &str, arguments: Vec<Value>) -> Result<Self, Error> {
+                check_count(target, &arguments, count!($($name)*))?;
+                let mut arguments = arguments.into_iter();
+                $(let $name = from_argument(target, arguments.next().unwrap_or(Value::Null))?;)*
+                Ok(($($name,)*))
+            }
+        }
+    };
+}
+
+tuple_arguments!();
+tuple_arguments!(A);
+tuple_arguments!(A, B);
+tuple_arguments!(A, B, C);
+tuple_arguments!(A, B, C, D);
+tuple_arguments!(A, B, C, D, E);
+tuple_arguments!(A, B, C, D, E, F);
+tuple_arguments!(A, B, C, D, E, F, G);
+tuple_arguments!(A, B, C, D, E, F, G, H);
+tuple_arguments!(A, B, C, D, E, F, G, H, I);
+tuple_arguments!(A, B, C, D, E, F, G, H, I, J);
+tuple_arguments!(A, B, C, D, E, F, G, H, I, J, K);
+tuple_arguments!(A, B, C, D, E, F, G, H, I, J, K, L);
+tuple_arguments!(A, B, C, D, E, F, G, H, I, J, K, L, M);
+tuple_arguments!(A, B, C, D, E, F, G, H, I, J, K, L, M, N);
+tuple_arguments!(A, B, C, D, E, F, G, H, I, J, K, L, M, N, O);
+tuple_arguments!(A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P);
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::{FromHubArgs, IntoHubArgs};
+    use crate::error::ErrorKind;
+
+    #[test]
+    fn tuples_round_trip_through_arguments() {
+        let arguments = ("rust", 3, Some(1.5)).into_hub_args().unwrap();
+        assert_eq!(arguments, vec![json!("rust"), json!(3), json!(1.5)]);
+
+        let (user, count, ratio) = <(String, u32, Option<f64>)>::from_hub_args("Target", arguments).unwrap();
+        assert_eq!((user.as_str(), count, ratio), ("rust", 3, Some(1.5)));
+        assert!(().into_hub_args().unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_mismatched_arguments() {
+        let error = <(String,)>::from_hub_args("Target", vec![]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidArguments);
+        let error = <(u32,)>::from_hub_args("Target", vec![Value::from("text")]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidArguments);
+    }
+}
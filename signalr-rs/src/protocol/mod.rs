@@ -1,21 +1,102 @@
+//! The JSON hub protocol and the negotiation that precedes it.
+//!
+//! The MessagePack hub protocol is not supported: the handshake always asks for `json` and
+//! binary frames are ignored.
 
+
+pub mod arguments;
 pub mod responses;
 
-use responses::NegotiateRequest;
-use reqwest;
-
-pub(crate) async fn start_negotiation(client: &reqwest::Client, url: &str) -> Option<NegotiateRequest> {
-    let url = format!("{}/negotiate?negotiateVersion=1", url);
-    let result = client.post(&url)
-                                                .header("Content-Length", "0")
-                                                .send()
-                                                .await;
-    if let Err(_) = result { return None; }
-    let result = result.unwrap(); 
-    if result.status().is_success() {
-        let response = result.json::<NegotiateRequest>().await;
-        Some(response.unwrap())
+use responses::{Handshake, Messsage, NegotiateRequest, MESSAGE_ENDING_BYTE};
+use crate::{error::Error, runtime::Transport};
+
+const HANDSHAKE_PROTOCOL: &str = "json";
+const HANDSHAKE_VERSION: u32 = 1;
+
+pub(crate) async fn start_negotiation(transport: &dyn Transport, url: &str) -> Result<NegotiateRequest, Error> {
+    let body = transport.negotiate(&negotiate_url(url)).await?;
+    parse_negotiation(&body)
+}
+
+pub(crate) fn negotiate_url(url: &str) -> String {
+    format!("{}/negotiate?negotiateVersion=1", url)
+}
+
+/// Parses the body of a `/negotiate` response.
+pub fn parse_negotiation(body: &str) -> Result<NegotiateRequest, Error> {
+    serde_json::from_str::<NegotiateRequest>(body)
+        .map_err(|e| Error::negotiation_error(&e.to_string()))
+}
+
+/// Turns the `http(s)://` hub url into the `ws(s)://` url of the negotiated connection.
+pub(crate) fn websocket_url(hub_url: &str, token: &str) -> Result<String, Error> {
+    let url = if let Some(rest) = hub_url.strip_prefix("https://") {
+        format!("wss://{}", rest)
+    } else if let Some(rest) = hub_url.strip_prefix("http://") {
+        format!("ws://{}", rest)
     } else {
-        None
+        return Err(Error::configuration_error("hub url must start with http:// or https://"));
+    };
+    Ok(format!("{}?id={}", url, encode_query_value(token)))
+}
+
+/// Percent-encodes `value` for a query string, ASP.NET Core tokens are base64 and hold `+`, `/` and `=`.
+fn encode_query_value(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => char::from(byte).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// The handshake request record, terminator included.
+pub(crate) fn handshake_request() -> String {
+    let request = Handshake::Request {
+        protocol: HANDSHAKE_PROTOCOL.to_owned(),
+        version: HANDSHAKE_VERSION,
+    };
+    serde_json::to_string(&request).unwrap() + MESSAGE_ENDING_BYTE
+}
+
+/// Parses the frame answering the handshake, returning the messages that followed the response.
+pub fn parse_handshake_response(payload: &str) -> Result<Vec<Messsage>, Error> {
+    let mut records = split_records(payload);
+    let response = records.next().ok_or_else(Error::handshake_error_simple)?;
+    match serde_json::from_str::<Handshake>(response) {
+        Ok(Handshake::Response { error: Some(error) }) => Err(Error::handshake_error(&error)),
+        Ok(Handshake::Response { error: None }) => Ok(records.filter_map(Messsage::deserialize).collect()),
+        _ => Err(Error::handshake_error_simple()),
+    }
+}
+
+/// Splits a text frame into the records delimited by the record separator.
+pub fn split_records(payload: &str) -> impl Iterator<Item = &str> {
+    payload.split(MESSAGE_ENDING_BYTE).filter(|record| !record.trim().is_empty())
+}
+
+/// Parses every message contained in a text frame, skipping records that fail to parse.
+pub fn parse_messages(payload: &str) -> impl Iterator<Item = Messsage> + '_ {
+    split_records(payload).filter_map(Messsage::deserialize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_handshake_response, parse_messages, parse_negotiation, websocket_url};
+
+    #[test]
+    fn malformed_input_is_rejected_without_panicking() {
+        let payload = "{\"type\":99}\u{1e}{\"type\":1}\u{1e}{\"type\":\"1\"}\u{1e}[]\u{1e}null\u{1e}{\"type\":6}\u{1e}";
+        assert_eq!(parse_messages(payload).count(), 1);
+        assert!(parse_handshake_response("{\"error\":7}\u{1e}").is_err());
+        assert!(parse_handshake_response("").is_err());
+        assert!(parse_negotiation("{\"connectionId\":null}").is_err());
+    }
+
+    #[test]
+    fn encodes_the_connection_token() {
+        let url = websocket_url("https://example.com/chat", "a+b/c=").unwrap();
+        assert_eq!(url, "wss://example.com/chat?id=a%2Bb%2Fc%3D");
     }
-}
\ No newline at end of file
+}
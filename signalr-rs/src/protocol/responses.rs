@@ -1,7 +1,9 @@
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
-const MESSAGE_ENDING_BYTE: &str = "\x1E";
+pub(crate) const MESSAGE_ENDING_BYTE: &str = "\x1E";
 
 #[derive(Deserialize, Serialize, Debug)]
 pub struct TransportDefinition {
@@ -23,33 +25,34 @@ pub struct NegotiateRequest {
     available_transports: Vec<TransportDefinition>,
 }
 
+impl NegotiateRequest {
+    pub fn connection_id(&self) -> &str {
+        &self.id
+    }
+
+    /// Names of the transports the server offered.
+    pub fn transports(&self) -> impl Iterator<Item = &str> {
+        self.available_transports.iter().map(|transport| transport.transport_name.as_str())
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug)]
 pub struct NegotiateResposne {
     protocol: String,
     version: u32,
 }
 
-#[derive(Deserialize, Serialize, Debug)]
-pub struct SocketMessage {
-    #[serde(rename = "type")]
-    req_type: u32,
-    target: Option<String>,
-    arguments: Option<Vec<String>>,
-    #[serde(rename = "invocationId")]
-    invocation_id: u32,
-}
-
 #[derive(Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum Handshake {    
-    Request { format: String, version: u32 },
+    Request { protocol: String, version: u32 },
     Response {
         #[serde(skip_serializing_if = "Option::is_none")]
         error: Option<String> 
     },
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Messsage {
     Invocation(InvocationFields), //type = 1
     StreamItem(StreamItemFields), // type = 2
@@ -60,60 +63,296 @@ pub enum Messsage {
     Close(CloseFields),
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// Headers carried by a message, see [`InvocationFields::with_headers`].
+///
+/// They round-trip through the JSON hub protocol only, MessagePack is not supported yet.
+pub type Headers = BTreeMap<String, String>;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
 pub struct InvocationFields {
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) headers: Headers,
     #[serde(skip_serializing_if = "Option::is_none")]
-    invocation_id: Option<String>, 
-    target: String, 
-    arguments: serde_json::Value
+    pub(crate) invocation_id: Option<String>,
+    pub(crate) target: String,
+    pub(crate) arguments: Vec<Value>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) stream_ids: Vec<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
 pub struct StreamItemFields {
-    #[serde(skip_serializing_if = "Option::is_none")]
-    invocation_id: Option<String>, 
-    item: serde_json::Value 
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) headers: Headers,
+    pub(crate) invocation_id: String,
+    pub(crate) item: Value,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
 pub struct CompletionFields {
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) headers: Headers,
+    pub(crate) invocation_id: String,
     #[serde(skip_serializing_if = "Option::is_none")]
-    invocation_id: Option<String>, 
-    #[serde(skip_serializing_if = "Option::is_none")]
-    error: Option<String>, 
-    result: serde_json::Value 
+    pub(crate) error: Option<String>,
+    /// `None` when the field is absent, `Some(Value::Null)` when the server sent `null`.
+    #[serde(default, deserialize_with = "present", skip_serializing_if = "Option::is_none")]
+    pub(crate) result: Option<Value>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
 pub struct StreamInvocationFields {
-    #[serde(skip_serializing_if = "Option::is_none")]
-    invocation_id: Option<String>, 
-    target: String, 
-    arguments: serde_json::Value
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) headers: Headers,
+    pub(crate) invocation_id: String,
+    pub(crate) target: String,
+    pub(crate) arguments: Vec<Value>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) stream_ids: Vec<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
 pub struct CancelInvokationFields {
-    #[serde(skip_serializing_if = "Option::is_none")]
-    invocation_id: Option<String>
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub(crate) headers: Headers,
+    pub(crate) invocation_id: String,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
 pub struct CloseFields {
     #[serde(skip_serializing_if = "Option::is_none")]
-    error: Option<String> 
+    pub(crate) error: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub(crate) allow_reconnect: bool,
+}
+
+/// Keeps a field that is present but `null` apart from a missing one.
+fn present<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<Value>, D::Error> {
+    Value::deserialize(deserializer).map(Some)
+}
+
+impl InvocationFields {
+    /// An invocation of `target`, the caller expects a completion when `invocation_id` is set.
+    pub fn new(invocation_id: Option<String>, target: &str, arguments: Vec<Value>) -> Self {
+        InvocationFields {
+            headers: Headers::new(),
+            invocation_id,
+            target: target.to_owned(),
+            arguments,
+            stream_ids: Vec::new(),
+        }
+    }
+
+    pub fn with_headers(mut self, headers: Headers) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    /// Ids of the streams the caller uploads as arguments.
+    pub fn with_stream_ids(mut self, stream_ids: Vec<String>) -> Self {
+        self.stream_ids = stream_ids;
+        self
+    }
+
+    pub fn headers(&self) -> &Headers {
+        &self.headers
+    }
+
+    pub fn invocation_id(&self) -> Option<&str> {
+        self.invocation_id.as_deref()
+    }
+
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    pub fn arguments(&self) -> &[Value] {
+        &self.arguments
+    }
+
+    pub fn stream_ids(&self) -> &[String] {
+        &self.stream_ids
+    }
+}
+
+impl StreamItemFields {
+    pub fn new(invocation_id: &str, item: Value) -> Self {
+        StreamItemFields {
+            headers: Headers::new(),
+            invocation_id: invocation_id.to_owned(),
+            item,
+        }
+    }
+
+    pub fn with_headers(mut self, headers: Headers) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    pub fn headers(&self) -> &Headers {
+        &self.headers
+    }
+
+    pub fn invocation_id(&self) -> &str {
+        &self.invocation_id
+    }
+
+    pub fn item(&self) -> &Value {
+        &self.item
+    }
+}
+
+impl CompletionFields {
+    /// Completes a method that returned `result`.
+    pub fn completed(invocation_id: &str, result: Value) -> Self {
+        Self::new(invocation_id, None, Some(result))
+    }
+
+    /// Completes a method that returned nothing, or a stream.
+    pub fn void(invocation_id: &str) -> Self {
+        Self::new(invocation_id, None, None)
+    }
+
+    pub fn failed(invocation_id: &str, error: &str) -> Self {
+        Self::new(invocation_id, Some(error.to_owned()), None)
+    }
+
+    fn new(invocation_id: &str, error: Option<String>, result: Option<Value>) -> Self {
+        CompletionFields {
+            headers: Headers::new(),
+            invocation_id: invocation_id.to_owned(),
+            error,
+            result,
+        }
+    }
+
+    pub fn with_headers(mut self, headers: Headers) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    pub fn headers(&self) -> &Headers {
+        &self.headers
+    }
+
+    pub fn invocation_id(&self) -> &str {
+        &self.invocation_id
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// The value the method returned, `None` when the completion carries no result at all.
+    pub fn result(&self) -> Option<&Value> {
+        self.result.as_ref()
+    }
+}
+
+impl StreamInvocationFields {
+    pub fn new(invocation_id: &str, target: &str, arguments: Vec<Value>) -> Self {
+        StreamInvocationFields {
+            headers: Headers::new(),
+            invocation_id: invocation_id.to_owned(),
+            target: target.to_owned(),
+            arguments,
+            stream_ids: Vec::new(),
+        }
+    }
+
+    pub fn with_headers(mut self, headers: Headers) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    /// Ids of the streams the caller uploads as arguments.
+    pub fn with_stream_ids(mut self, stream_ids: Vec<String>) -> Self {
+        self.stream_ids = stream_ids;
+        self
+    }
+
+    pub fn headers(&self) -> &Headers {
+        &self.headers
+    }
+
+    pub fn invocation_id(&self) -> &str {
+        &self.invocation_id
+    }
+
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    pub fn arguments(&self) -> &[Value] {
+        &self.arguments
+    }
+
+    pub fn stream_ids(&self) -> &[String] {
+        &self.stream_ids
+    }
+}
+
+impl CancelInvokationFields {
+    pub fn new(invocation_id: &str) -> Self {
+        CancelInvokationFields {
+            headers: Headers::new(),
+            invocation_id: invocation_id.to_owned(),
+        }
+    }
+
+    pub fn with_headers(mut self, headers: Headers) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    pub fn headers(&self) -> &Headers {
+        &self.headers
+    }
+
+    pub fn invocation_id(&self) -> &str {
+        &self.invocation_id
+    }
+}
+
+impl CloseFields {
+    pub fn new(error: Option<String>, allow_reconnect: bool) -> Self {
+        CloseFields { error, allow_reconnect }
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Whether the server lets the client reconnect after this close.
+    pub fn allow_reconnect(&self) -> bool {
+        self.allow_reconnect
+    }
 }
 
 impl Messsage {
-    pub fn deserialize(json: &str) -> Option<Self> {
-        let result = serde_json::from_str::<Self>(json);
-        if let Ok(obj) = result {
-            Some(obj)
-        } else {
-            None
+    /// Name of the message type, as in the protocol specification.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Messsage::Invocation(_) => "Invocation",
+            Messsage::StreamItem(_) => "StreamItem",
+            Messsage::Completion(_) => "Completion",
+            Messsage::StreamInvocation(_) => "StreamInvocation",
+            Messsage::CancelInvokation(_) => "CancelInvocation",
+            Messsage::Ping => "Ping",
+            Messsage::Close(_) => "Close",
         }
     }
 
+    pub fn deserialize(json: &str) -> Option<Self> {
+        serde_json::from_str::<Self>(json).ok()
+    }
+
     pub fn serialize(self) -> Option<String> {
         if let Ok(json) = serde_json::to_string(&self) {
             let json = json + MESSAGE_ENDING_BYTE;
@@ -168,16 +407,22 @@ impl<'de> serde::Deserialize<'de> for Messsage {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
         where
             D: serde::Deserializer<'de> {
+        use serde::de::Error;
+
         let value = Value::deserialize(deserializer)?;
-        Ok(match value.get("type").and_then(Value::as_u64).unwrap() {
-            1 => Messsage::Invocation(InvocationFields::deserialize(value).unwrap()),
-            2 => Messsage::StreamItem(StreamItemFields::deserialize(value).unwrap()),
-            3 => Messsage::Completion(CompletionFields::deserialize(value).unwrap()),
-            4 => Messsage::StreamInvocation(StreamInvocationFields::deserialize(value).unwrap()),
-            5 => Messsage::CancelInvokation(CancelInvokationFields::deserialize(value).unwrap()),
+        let message_type = value
+            .get("type")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| D::Error::missing_field("type"))?;
+        Ok(match message_type {
+            1 => Messsage::Invocation(InvocationFields::deserialize(value).map_err(D::Error::custom)?),
+            2 => Messsage::StreamItem(StreamItemFields::deserialize(value).map_err(D::Error::custom)?),
+            3 => Messsage::Completion(CompletionFields::deserialize(value).map_err(D::Error::custom)?),
+            4 => Messsage::StreamInvocation(StreamInvocationFields::deserialize(value).map_err(D::Error::custom)?),
+            5 => Messsage::CancelInvokation(CancelInvokationFields::deserialize(value).map_err(D::Error::custom)?),
             6 => Messsage::Ping,
-            7 => Messsage::Close(CloseFields::deserialize(value).unwrap()),
-            type_ => panic!("Unsupported type {:?}", type_),
+            7 => Messsage::Close(CloseFields::deserialize(value).map_err(D::Error::custom)?),
+            type_ => return Err(D::Error::custom(format!("unsupported message type {}", type_))),
         })
     }
 }
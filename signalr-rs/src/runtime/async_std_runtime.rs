@@ -0,0 +1,63 @@
+use std::time::Duration;
+
+use async_tungstenite::async_std::connect_async;
+use futures_util::{future, SinkExt, StreamExt};
+
+use super::{from_message, into_message, BoxFuture, Runtime, Transport, TransportSink, TransportStream};
+use crate::error::Error;
+
+/// Runs the connection on the async-std global executor.
+pub struct AsyncStdRuntime;
+
+impl Runtime for AsyncStdRuntime {
+    fn spawn(&self, task: BoxFuture<'static, ()>) {
+        async_std::task::spawn(task);
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        Box::pin(async_std::task::sleep(duration))
+    }
+}
+
+/// Negotiates with `surf` and connects with `async-tungstenite`.
+#[derive(Default)]
+pub struct AsyncStdTransport {
+    client: surf::Client,
+}
+
+impl AsyncStdTransport {
+    pub fn new() -> Self {
+        AsyncStdTransport { client: surf::Client::new() }
+    }
+}
+
+impl Transport for AsyncStdTransport {
+    fn negotiate<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<String, Error>> {
+        Box::pin(async move {
+            let mut response = self.client.post(url)
+                .header("Content-Length", "0")
+                .await
+                .map_err(|e| Error::negotiation_error(&e.to_string()))?;
+            if !response.status().is_success() {
+                return Err(Error::negotiation_error(&format!("server answered {}", response.status())));
+            }
+            response.body_string()
+                .await
+                .map_err(|e| Error::negotiation_error(&e.to_string()))
+        })
+    }
+
+    fn connect<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<(TransportSink, TransportStream), Error>> {
+        Box::pin(async move {
+            let (socket, _) = connect_async(url)
+                .await
+                .map_err(|e| Error::transport_error(&e.to_string()))?;
+            let (sink, stream) = socket.split();
+            let sink = sink
+                .sink_map_err(|e| Error::transport_error(&e.to_string()))
+                .with(|frame| future::ready(Ok::<_, Error>(into_message(frame))));
+            let stream = stream.filter_map(|message| future::ready(from_message(message)));
+            Ok((Box::pin(sink) as TransportSink, Box::pin(stream) as TransportStream))
+        })
+    }
+}
@@ -0,0 +1,163 @@
+//! The executor, timer and transport the connection core runs on.
+//!
+//! [`HubConnection`](crate::HubConnection) never talks to an executor or a socket directly, it
+//! goes through [`Runtime`] and [`Transport`]. The `tokio` feature (on by default) and the
+//! `async-std` feature provide ready made implementations, anything else can be plugged in with
+//! [`HubConnectionBuilder::with_runtime`](crate::HubConnectionBuilder::with_runtime) and
+//! [`HubConnectionBuilder::with_transport`](crate::HubConnectionBuilder::with_transport).
+//!
+//! Both features can be enabled at once, features are additive and another crate of the
+//! dependency graph may turn on the one you did not ask for. `tokio` then takes precedence as
+//! the default, pass `AsyncStdRuntime` and `AsyncStdTransport` explicitly to run on async-std.
+
+#[cfg(feature = "tokio")]
+mod tokio_runtime;
+#[cfg(feature = "async-std")]
+mod async_std_runtime;
+
+#[cfg(feature = "tokio")]
+pub use tokio_runtime::{TokioRuntime, TokioTransport};
+#[cfg(feature = "async-std")]
+pub use async_std_runtime::{AsyncStdRuntime, AsyncStdTransport};
+
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures_util::{
+    future::{self, AbortHandle, Either},
+    Sink, Stream,
+};
+use tokio::sync::oneshot;
+
+use crate::error::Error;
+
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Outgoing half of an open transport, [`Frame::Close`] sends a close frame with the Normal status.
+pub type TransportSink = Pin<Box<dyn Sink<Frame, Error = Error> + Send>>;
+
+/// Incoming half of an open transport, ends when the transport is gone.
+pub type TransportStream = Pin<Box<dyn Stream<Item = Result<Frame, Error>> + Send>>;
+
+/// A frame exchanged with the server, control frames other than close never reach the core.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Frame {
+    Text(String),
+    Binary(Vec<u8>),
+    Close,
+}
+
+/// Spawns the background tasks of a connection and provides its timers.
+pub trait Runtime: Send + Sync + 'static {
+    fn spawn(&self, task: BoxFuture<'static, ()>);
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()>;
+}
+
+/// Performs the HTTP negotiation and opens the WebSocket of a connection.
+pub trait Transport: Send + Sync + 'static {
+    /// POSTs to `url` and returns the response body.
+    fn negotiate<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<String, Error>>;
+
+    fn connect<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<(TransportSink, TransportStream), Error>>;
+}
+
+/// The runtime of the enabled feature, `tokio` when both are.
+#[allow(unreachable_code)]
+pub(crate) fn default_runtime() -> Option<Arc<dyn Runtime>> {
+    #[cfg(feature = "tokio")]
+    return Some(Arc::new(TokioRuntime));
+    #[cfg(feature = "async-std")]
+    return Some(Arc::new(AsyncStdRuntime));
+    None
+}
+
+/// The transport of the enabled feature, `tokio` when both are.
+#[allow(unreachable_code)]
+pub(crate) fn default_transport() -> Option<Result<Arc<dyn Transport>, Error>> {
+    #[cfg(feature = "tokio")]
+    return Some(TokioTransport::new().map(|t| Arc::new(t) as Arc<dyn Transport>));
+    #[cfg(feature = "async-std")]
+    return Some(Ok(Arc::new(AsyncStdTransport::new())));
+    None
+}
+
+/// Handle to a task started with [`spawn`], resolves once the task finished or was aborted.
+pub(crate) struct TaskHandle {
+    abort: AbortHandle,
+    done: oneshot::Receiver<()>,
+}
+
+impl TaskHandle {
+    pub(crate) fn abort(&self) {
+        self.abort.abort();
+    }
+}
+
+impl Future for TaskHandle {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        Pin::new(&mut self.done).poll(cx).map(|_| ())
+    }
+}
+
+pub(crate) fn spawn<F>(runtime: &dyn Runtime, task: F) -> TaskHandle
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    let (task, abort) = future::abortable(task);
+    let (done_sender, done) = oneshot::channel();
+    runtime.spawn(Box::pin(async move {
+        let _ = task.await;
+        let _ = done_sender.send(());
+    }));
+    TaskHandle { abort, done }
+}
+
+/// Returned by [`timeout`] when the deadline elapsed first.
+#[derive(Debug)]
+pub(crate) struct Elapsed;
+
+pub(crate) async fn timeout<F: Future>(runtime: &dyn Runtime, duration: Duration, future: F) -> Result<F::Output, Elapsed> {
+    let sleep = runtime.sleep(duration);
+    futures_util::pin_mut!(future);
+    match future::select(future, sleep).await {
+        Either::Left((output, _)) => Ok(output),
+        Either::Right(_) => Err(Elapsed),
+    }
+}
+
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+fn into_message(frame: Frame) -> tungstenite::Message {
+    match frame {
+        Frame::Text(text) => tungstenite::Message::Text(text),
+        Frame::Binary(data) => tungstenite::Message::Binary(data),
+        Frame::Close => tungstenite::Message::Close(Some(normal_closure())),
+    }
+}
+
+/// The close frame of a connection stopped on purpose, as browsers and the .NET client send it.
+#[cfg(any(feature = "tokio", feature = "async-std", feature = "blocking"))]
+pub(crate) fn normal_closure() -> tungstenite::protocol::CloseFrame<'static> {
+    tungstenite::protocol::CloseFrame {
+        code: tungstenite::protocol::frame::coding::CloseCode::Normal,
+        reason: "".into(),
+    }
+}
+
+#[cfg(any(feature = "tokio", feature = "async-std"))]
+fn from_message(message: Result<tungstenite::Message, tungstenite::Error>) -> Option<Result<Frame, Error>> {
+    match message {
+        Ok(tungstenite::Message::Text(text)) => Some(Ok(Frame::Text(text))),
+        Ok(tungstenite::Message::Binary(data)) => Some(Ok(Frame::Binary(data))),
+        Ok(tungstenite::Message::Close(_)) => Some(Ok(Frame::Close)),
+        Ok(_) => None,
+        Err(e) => Some(Err(Error::transport_error(&e.to_string()))),
+    }
+}
@@ -0,0 +1,67 @@
+use std::time::Duration;
+
+use futures_util::{future, SinkExt, StreamExt};
+use reqwest::Client;
+use tokio_tungstenite::connect_async;
+
+use super::{from_message, into_message, BoxFuture, Runtime, Transport, TransportSink, TransportStream};
+use crate::error::Error;
+
+/// Runs the connection on the ambient tokio runtime.
+pub struct TokioRuntime;
+
+impl Runtime for TokioRuntime {
+    fn spawn(&self, task: BoxFuture<'static, ()>) {
+        tokio::spawn(task);
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+/// Negotiates with `reqwest` and connects with `tokio-tungstenite`.
+pub struct TokioTransport {
+    client: Client,
+}
+
+impl TokioTransport {
+    pub fn new() -> Result<Self, Error> {
+        let client = Client::builder()
+            .build()
+            .map_err(|e| Error::configuration_error(&e.to_string()))?;
+        Ok(TokioTransport { client })
+    }
+}
+
+impl Transport for TokioTransport {
+    fn negotiate<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<String, Error>> {
+        Box::pin(async move {
+            let response = self.client.post(url)
+                .header("Content-Length", "0")
+                .send()
+                .await
+                .map_err(|e| Error::negotiation_error(&e.to_string()))?;
+            if !response.status().is_success() {
+                return Err(Error::negotiation_error(&format!("server answered {}", response.status())));
+            }
+            response.text()
+                .await
+                .map_err(|e| Error::negotiation_error(&e.to_string()))
+        })
+    }
+
+    fn connect<'a>(&'a self, url: &'a str) -> BoxFuture<'a, Result<(TransportSink, TransportStream), Error>> {
+        Box::pin(async move {
+            let (socket, _) = connect_async(url)
+                .await
+                .map_err(|e| Error::transport_error(&e.to_string()))?;
+            let (sink, stream) = socket.split();
+            let sink = sink
+                .sink_map_err(|e| Error::transport_error(&e.to_string()))
+                .with(|frame| future::ready(Ok::<_, Error>(into_message(frame))));
+            let stream = stream.filter_map(|message| future::ready(from_message(message)));
+            Ok((Box::pin(sink) as TransportSink, Box::pin(stream) as TransportStream))
+        })
+    }
+}
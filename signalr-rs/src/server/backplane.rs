@@ -0,0 +1,247 @@
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hash, Hasher},
+    pin::Pin,
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use futures_util::{future, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::{sync::mpsc, task::JoinHandle};
+
+use crate::{error::Error, runtime::BoxFuture};
+
+use super::lifetime::{ClientTarget, DefaultHubLifetimeManager, HubClient, HubLifetimeManager};
+
+/// A message exchanged between the nodes serving a hub.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BackplaneMessage {
+    /// Node that published the message.
+    pub node_id: String,
+    /// The serialized operation, opaque to the backplane.
+    pub payload: Vec<u8>,
+}
+
+/// Messages published by every node, the node's own included.
+pub type BackplaneStream = Pin<Box<dyn Stream<Item = BackplaneMessage> + Send>>;
+
+/// Carries hub operations between the nodes of a scaled out hub.
+///
+/// Adapters for brokers such as Redis or NATS implement it in their own crates, the
+/// [`BackplaneHubLifetimeManager`] only needs every published message delivered to every
+/// subscriber in publication order.
+pub trait Backplane: Send + Sync + 'static {
+    fn publish(&self, message: BackplaneMessage) -> BoxFuture<'_, Result<(), Error>>;
+
+    fn subscribe<'a>(&'a self, node_id: &'a str) -> BoxFuture<'a, Result<BackplaneStream, Error>>;
+}
+
+/// A backplane connecting the nodes of a single process, for tests and local development.
+///
+/// Clones share the same channel, give one to the lifetime manager of every node.
+#[derive(Clone, Default)]
+pub struct InMemoryBackplane {
+    subscribers: Arc<Mutex<Vec<mpsc::UnboundedSender<BackplaneMessage>>>>,
+}
+
+impl InMemoryBackplane {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Backplane for InMemoryBackplane {
+    fn publish(&self, message: BackplaneMessage) -> BoxFuture<'_, Result<(), Error>> {
+        self.subscribers
+            .lock()
+            .unwrap()
+            .retain(|subscriber| subscriber.send(message.clone()).is_ok());
+        Box::pin(future::ready(Ok(())))
+    }
+
+    fn subscribe<'a>(&'a self, _node_id: &'a str) -> BoxFuture<'a, Result<BackplaneStream, Error>> {
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        self.subscribers.lock().unwrap().push(sender);
+        let stream = futures_util::stream::poll_fn(move |cx| receiver.poll_recv(cx));
+        Box::pin(future::ready(Ok(Box::pin(stream) as BackplaneStream)))
+    }
+}
+
+/// Operations a node forwards to the others.
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "operation", rename_all = "camelCase")]
+enum Operation {
+    Send {
+        target: ClientTarget,
+        method: String,
+        arguments: Vec<Value>,
+    },
+    #[serde(rename_all = "camelCase")]
+    AddToGroup { connection_id: String, group: String },
+    #[serde(rename_all = "camelCase")]
+    RemoveFromGroup { connection_id: String, group: String },
+}
+
+impl Operation {
+    fn apply(self, local: &DefaultHubLifetimeManager) {
+        match self {
+            Operation::Send {
+                target,
+                method,
+                arguments,
+            } => local.send_local(&target, &method, &arguments),
+            Operation::AddToGroup { connection_id, group } => local.add_local_to_group(&connection_id, &group),
+            Operation::RemoveFromGroup { connection_id, group } => local.remove_local_from_group(&connection_id, &group),
+        }
+    }
+}
+
+/// A lifetime manager sharing sends and group changes with the other nodes through a [`Backplane`].
+///
+/// Every node keeps its own connections and their groups, operations are applied locally and
+/// published for the other nodes to apply to theirs.
+pub struct BackplaneHubLifetimeManager {
+    node_id: String,
+    local: Arc<DefaultHubLifetimeManager>,
+    backplane: Arc<dyn Backplane>,
+    subscription: JoinHandle<()>,
+}
+
+impl BackplaneHubLifetimeManager {
+    /// Subscribes to `backplane` under a fresh node id, must be called within a tokio runtime.
+    pub async fn new<B: Backplane>(backplane: B) -> Result<Self, Error> {
+        let node_id = new_node_id();
+        let backplane: Arc<dyn Backplane> = Arc::new(backplane);
+        let local = Arc::new(DefaultHubLifetimeManager::new());
+        let mut messages = backplane.subscribe(&node_id).await?;
+        let subscription = {
+            let node_id = node_id.clone();
+            let local = local.clone();
+            tokio::spawn(async move {
+                while let Some(message) = messages.next().await {
+                    if message.node_id == node_id {
+                        continue;
+                    }
+                    if let Ok(operation) = serde_json::from_slice::<Operation>(&message.payload) {
+                        operation.apply(&local);
+                    }
+                }
+            })
+        };
+        Ok(BackplaneHubLifetimeManager {
+            node_id,
+            local,
+            backplane,
+            subscription,
+        })
+    }
+
+    /// Id this node publishes under.
+    pub fn node_id(&self) -> &str {
+        &self.node_id
+    }
+
+    async fn publish(&self, operation: Operation) -> Result<(), Error> {
+        let payload = serde_json::to_vec(&operation).map_err(|e| Error::protocol_error(&e.to_string()))?;
+        self.backplane
+            .publish(BackplaneMessage {
+                node_id: self.node_id.clone(),
+                payload,
+            })
+            .await
+    }
+}
+
+impl Drop for BackplaneHubLifetimeManager {
+    fn drop(&mut self) {
+        self.subscription.abort();
+    }
+}
+
+impl HubLifetimeManager for BackplaneHubLifetimeManager {
+    fn on_connected(&self, client: HubClient) -> BoxFuture<'_, Result<(), Error>> {
+        self.local.on_connected(client)
+    }
+
+    fn on_disconnected<'a>(&'a self, client: &'a HubClient) -> BoxFuture<'a, ()> {
+        self.local.on_disconnected(client)
+    }
+
+    fn send<'a>(&'a self, target: ClientTarget, method: &'a str, arguments: Vec<Value>) -> BoxFuture<'a, Result<(), Error>> {
+        self.local.send_local(&target, method, &arguments);
+        let operation = Operation::Send {
+            target,
+            method: method.to_owned(),
+            arguments,
+        };
+        Box::pin(self.publish(operation))
+    }
+
+    fn add_to_group<'a>(&'a self, connection_id: &'a str, group: &'a str) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            if self.local.is_local(connection_id) {
+                self.local.add_local_to_group(connection_id, group);
+                return Ok(());
+            }
+            self.publish(Operation::AddToGroup {
+                connection_id: connection_id.to_owned(),
+                group: group.to_owned(),
+            })
+            .await
+        })
+    }
+
+    fn remove_from_group<'a>(&'a self, connection_id: &'a str, group: &'a str) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            if self.local.is_local(connection_id) {
+                self.local.remove_local_from_group(connection_id, group);
+                return Ok(());
+            }
+            self.publish(Operation::RemoveFromGroup {
+                connection_id: connection_id.to_owned(),
+                group: group.to_owned(),
+            })
+            .await
+        })
+    }
+}
+
+fn new_node_id() -> String {
+    let mut hasher = RandomState::new().build_hasher();
+    SystemTime::now().hash(&mut hasher);
+    std::process::id().hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use serde_json::json;
+    use tokio::{sync::mpsc, time};
+
+    use super::{BackplaneHubLifetimeManager, InMemoryBackplane};
+    use crate::server::lifetime::{ClientTarget, HubClient, HubLifetimeManager};
+
+    #[tokio::test]
+    async fn forwards_group_changes_and_sends_between_nodes() {
+        let backplane = InMemoryBackplane::new();
+        let first = BackplaneHubLifetimeManager::new(backplane.clone()).await.unwrap();
+        let second = BackplaneHubLifetimeManager::new(backplane).await.unwrap();
+        let (outbound, mut received) = mpsc::unbounded_channel();
+        first.on_connected(HubClient::new("remote".to_owned(), None, outbound)).await.unwrap();
+
+        second.add_to_group("remote", "room").await.unwrap();
+        let room = ClientTarget::Groups {
+            groups: vec!["room".to_owned()],
+            except: vec![],
+        };
+        second.send(room, "Receive", vec![json!("hi")]).await.unwrap();
+
+        let message = time::timeout(Duration::from_secs(5), received.recv()).await.unwrap();
+        assert!(message.is_some());
+        assert!(received.try_recv().is_err());
+    }
+}
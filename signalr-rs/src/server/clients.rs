@@ -0,0 +1,130 @@
+use std::sync::Arc;
+
+use crate::{error::Error, protocol::arguments::IntoHubArgs};
+
+use super::lifetime::{ClientTarget, HubLifetimeManager};
+
+fn owned<I: IntoIterator<Item = S>, S: Into<String>>(values: I) -> Vec<String> {
+    values.into_iter().map(Into::into).collect()
+}
+
+/// Picks the connections a hub sends to, like `Clients` on an ASP.NET Core hub.
+#[derive(Clone)]
+pub struct Clients {
+    manager: Arc<dyn HubLifetimeManager>,
+    caller: Option<String>,
+}
+
+impl Clients {
+    pub(crate) fn new(manager: Arc<dyn HubLifetimeManager>, caller: Option<String>) -> Self {
+        Clients { manager, caller }
+    }
+
+    /// Every connected client.
+    pub fn all(&self) -> ClientProxy {
+        self.proxy(ClientTarget::All { except: Vec::new() })
+    }
+
+    /// Every connected client but the ones in `connection_ids`.
+    pub fn all_except<I: IntoIterator<Item = S>, S: Into<String>>(&self, connection_ids: I) -> ClientProxy {
+        self.proxy(ClientTarget::All {
+            except: owned(connection_ids),
+        })
+    }
+
+    /// The client whose invocation is being handled, nobody outside of a handler.
+    pub fn caller(&self) -> ClientProxy {
+        self.proxy(ClientTarget::Connections(self.caller.iter().cloned().collect()))
+    }
+
+    /// Every client but the caller.
+    pub fn others(&self) -> ClientProxy {
+        self.all_except(self.caller.iter().cloned())
+    }
+
+    /// A single connection.
+    pub fn client(&self, connection_id: &str) -> ClientProxy {
+        self.clients([connection_id])
+    }
+
+    pub fn clients<I: IntoIterator<Item = S>, S: Into<String>>(&self, connection_ids: I) -> ClientProxy {
+        self.proxy(ClientTarget::Connections(owned(connection_ids)))
+    }
+
+    /// Every member of `group`.
+    pub fn group(&self, group: &str) -> ClientProxy {
+        self.groups([group])
+    }
+
+    /// Every member of any of `groups`, once even when in several.
+    pub fn groups<I: IntoIterator<Item = S>, S: Into<String>>(&self, groups: I) -> ClientProxy {
+        self.proxy(ClientTarget::Groups {
+            groups: owned(groups),
+            except: Vec::new(),
+        })
+    }
+
+    /// Every member of `group` but the connections in `connection_ids`.
+    pub fn group_except<I: IntoIterator<Item = S>, S: Into<String>>(&self, group: &str, connection_ids: I) -> ClientProxy {
+        self.proxy(ClientTarget::Groups {
+            groups: vec![group.to_owned()],
+            except: owned(connection_ids),
+        })
+    }
+
+    /// Every member of `group` but the caller.
+    pub fn others_in_group(&self, group: &str) -> ClientProxy {
+        self.group_except(group, self.caller.iter().cloned())
+    }
+
+    /// Every connection of `user_id`.
+    pub fn user(&self, user_id: &str) -> ClientProxy {
+        self.users([user_id])
+    }
+
+    pub fn users<I: IntoIterator<Item = S>, S: Into<String>>(&self, user_ids: I) -> ClientProxy {
+        self.proxy(ClientTarget::Users(owned(user_ids)))
+    }
+
+    fn proxy(&self, target: ClientTarget) -> ClientProxy {
+        ClientProxy {
+            manager: self.manager.clone(),
+            target,
+        }
+    }
+}
+
+/// A set of clients that can be invoked.
+pub struct ClientProxy {
+    manager: Arc<dyn HubLifetimeManager>,
+    target: ClientTarget,
+}
+
+impl ClientProxy {
+    /// Invokes `method` on every client of the set, without waiting for them to receive it.
+    pub async fn send<A: IntoHubArgs>(&self, method: &str, arguments: A) -> Result<(), Error> {
+        let arguments = arguments.into_hub_args()?;
+        self.manager.send(self.target.clone(), method, arguments).await
+    }
+}
+
+/// Manages group membership, like `Groups` on an ASP.NET Core hub.
+#[derive(Clone)]
+pub struct Groups {
+    manager: Arc<dyn HubLifetimeManager>,
+}
+
+impl Groups {
+    pub(crate) fn new(manager: Arc<dyn HubLifetimeManager>) -> Self {
+        Groups { manager }
+    }
+
+    /// Adds `connection_id` to `group`, connections leave their groups when they disconnect.
+    pub async fn add_to_group(&self, connection_id: &str, group: &str) -> Result<(), Error> {
+        self.manager.add_to_group(connection_id, group).await
+    }
+
+    pub async fn remove_from_group(&self, connection_id: &str, group: &str) -> Result<(), Error> {
+        self.manager.remove_from_group(connection_id, group).await
+    }
+}
@@ -0,0 +1,282 @@
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    sync::Mutex,
+};
+
+use futures_util::future;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::mpsc;
+
+use crate::{
+    error::Error,
+    protocol::responses::{InvocationFields, Messsage},
+    runtime::BoxFuture,
+};
+
+/// A client connected to this process, as handed to the [`HubLifetimeManager`].
+#[derive(Clone)]
+pub struct HubClient {
+    connection_id: String,
+    user_id: Option<String>,
+    outbound: mpsc::UnboundedSender<Messsage>,
+}
+
+impl HubClient {
+    pub(crate) fn new(connection_id: String, user_id: Option<String>, outbound: mpsc::UnboundedSender<Messsage>) -> Self {
+        HubClient {
+            connection_id,
+            user_id,
+            outbound,
+        }
+    }
+
+    pub fn connection_id(&self) -> &str {
+        &self.connection_id
+    }
+
+    /// User the connection was authenticated as, see [`HubBuilder::with_user_id_provider`](super::HubBuilder::with_user_id_provider).
+    pub fn user_id(&self) -> Option<&str> {
+        self.user_id.as_deref()
+    }
+
+    /// Whether `other` is this very connection, not merely one with the same id.
+    pub fn is_same_connection(&self, other: &HubClient) -> bool {
+        self.connection_id == other.connection_id && self.outbound.same_channel(&other.outbound)
+    }
+
+    /// Queues an invocation of `method` on the client, returns `false` once the client is gone.
+    pub fn invoke(&self, method: &str, arguments: Vec<Value>) -> bool {
+        self.outbound
+            .send(Messsage::Invocation(InvocationFields::new(None, method, arguments)))
+            .is_ok()
+    }
+}
+
+/// The clients a send is addressed to.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ClientTarget {
+    /// Every client but the connections in `except`.
+    All { except: Vec<String> },
+    Connections(Vec<String>),
+    /// Members of any of `groups` but the connections in `except`.
+    Groups { groups: Vec<String>, except: Vec<String> },
+    /// Every connection of any of the users.
+    Users(Vec<String>),
+}
+
+impl ClientTarget {
+    /// Whether `client`, member of `groups`, is addressed.
+    pub fn includes(&self, client: &HubClient, groups: &HashSet<String>) -> bool {
+        let id = client.connection_id();
+        match self {
+            ClientTarget::All { except } => !except.iter().any(|excluded| excluded == id),
+            ClientTarget::Connections(ids) => ids.iter().any(|included| included == id),
+            ClientTarget::Groups { groups: targets, except } => {
+                targets.iter().any(|group| groups.contains(group)) && !except.iter().any(|excluded| excluded == id)
+            }
+            ClientTarget::Users(users) => client.user_id().is_some_and(|user| users.iter().any(|target| target == user)),
+        }
+    }
+}
+
+/// Tracks the connections and groups of a hub and delivers the invocations sent to them.
+///
+/// The default, [`DefaultHubLifetimeManager`], only knows about the clients of this process;
+/// an implementation can share them between processes to scale a hub out.
+pub trait HubLifetimeManager: Send + Sync + 'static {
+    /// `client` completed its handshake, refused if its connection id is already connected.
+    fn on_connected(&self, client: HubClient) -> BoxFuture<'_, Result<(), Error>>;
+
+    /// `client` is gone, it must be removed from every group.
+    ///
+    /// Only `client` itself is removed, see [`HubClient::is_same_connection`], never another
+    /// connection registered under the same id.
+    fn on_disconnected<'a>(&'a self, client: &'a HubClient) -> BoxFuture<'a, ()>;
+
+    /// Invokes `method` on every client addressed by `target`.
+    fn send<'a>(&'a self, target: ClientTarget, method: &'a str, arguments: Vec<Value>) -> BoxFuture<'a, Result<(), Error>>;
+
+    fn add_to_group<'a>(&'a self, connection_id: &'a str, group: &'a str) -> BoxFuture<'a, Result<(), Error>>;
+
+    fn remove_from_group<'a>(&'a self, connection_id: &'a str, group: &'a str) -> BoxFuture<'a, Result<(), Error>>;
+}
+
+#[derive(Default)]
+struct Registry {
+    clients: HashMap<String, HubClient>,
+    /// Groups of every connection, a connection without groups has no entry.
+    groups: HashMap<String, HashSet<String>>,
+}
+
+/// Keeps connections and groups in memory, for hubs served by a single process.
+#[derive(Default)]
+pub struct DefaultHubLifetimeManager {
+    registry: Mutex<Registry>,
+}
+
+impl DefaultHubLifetimeManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Invokes `method` on the local clients addressed by `target`.
+    pub fn send_local(&self, target: &ClientTarget, method: &str, arguments: &[Value]) {
+        let recipients: Vec<HubClient> = {
+            let registry = self.registry.lock().unwrap();
+            let no_groups = HashSet::new();
+            registry
+                .clients
+                .values()
+                .filter(|client| {
+                    let groups = registry.groups.get(client.connection_id()).unwrap_or(&no_groups);
+                    target.includes(client, groups)
+                })
+                .cloned()
+                .collect()
+        };
+        for client in recipients {
+            client.invoke(method, arguments.to_vec());
+        }
+    }
+
+    /// Whether `connection_id` is connected to this process.
+    pub fn is_local(&self, connection_id: &str) -> bool {
+        self.registry.lock().unwrap().clients.contains_key(connection_id)
+    }
+
+    pub(crate) fn add_local_to_group(&self, connection_id: &str, group: &str) {
+        let mut registry = self.registry.lock().unwrap();
+        // Like ASP.NET Core, connections that are not connected here are ignored.
+        if registry.clients.contains_key(connection_id) {
+            registry
+                .groups
+                .entry(connection_id.to_owned())
+                .or_default()
+                .insert(group.to_owned());
+        }
+    }
+
+    pub(crate) fn remove_local_from_group(&self, connection_id: &str, group: &str) {
+        let mut registry = self.registry.lock().unwrap();
+        if let Some(groups) = registry.groups.get_mut(connection_id) {
+            groups.remove(group);
+            if groups.is_empty() {
+                registry.groups.remove(connection_id);
+            }
+        }
+    }
+}
+
+impl HubLifetimeManager for DefaultHubLifetimeManager {
+    fn on_connected(&self, client: HubClient) -> BoxFuture<'_, Result<(), Error>> {
+        let mut registry = self.registry.lock().unwrap();
+        let result = match registry.clients.entry(client.connection_id().to_owned()) {
+            Entry::Occupied(_) => Err(Error::duplicate_connection_error(client.connection_id())),
+            Entry::Vacant(entry) => {
+                entry.insert(client);
+                Ok(())
+            }
+        };
+        Box::pin(future::ready(result))
+    }
+
+    fn on_disconnected<'a>(&'a self, client: &'a HubClient) -> BoxFuture<'a, ()> {
+        let mut registry = self.registry.lock().unwrap();
+        let id = client.connection_id();
+        if registry.clients.get(id).is_some_and(|registered| registered.is_same_connection(client)) {
+            registry.clients.remove(id);
+            registry.groups.remove(id);
+        }
+        Box::pin(future::ready(()))
+    }
+
+    fn send<'a>(&'a self, target: ClientTarget, method: &'a str, arguments: Vec<Value>) -> BoxFuture<'a, Result<(), Error>> {
+        self.send_local(&target, method, &arguments);
+        Box::pin(future::ready(Ok(())))
+    }
+
+    fn add_to_group<'a>(&'a self, connection_id: &'a str, group: &'a str) -> BoxFuture<'a, Result<(), Error>> {
+        self.add_local_to_group(connection_id, group);
+        Box::pin(future::ready(Ok(())))
+    }
+
+    fn remove_from_group<'a>(&'a self, connection_id: &'a str, group: &'a str) -> BoxFuture<'a, Result<(), Error>> {
+        self.remove_local_from_group(connection_id, group);
+        Box::pin(future::ready(Ok(())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tokio::sync::mpsc;
+
+    use super::{ClientTarget, DefaultHubLifetimeManager, HubClient, HubLifetimeManager};
+    use crate::{error::ErrorKind, protocol::responses::Messsage};
+
+    fn client(id: &str, user: &str) -> (HubClient, mpsc::UnboundedReceiver<Messsage>) {
+        let (outbound, receiver) = mpsc::unbounded_channel();
+        (HubClient::new(id.to_owned(), Some(user.to_owned()), outbound), receiver)
+    }
+
+    async fn connect(manager: &DefaultHubLifetimeManager, id: &str, user: &str) -> mpsc::UnboundedReceiver<Messsage> {
+        let (client, receiver) = client(id, user);
+        manager.on_connected(client).await.unwrap();
+        receiver
+    }
+
+    fn received(receiver: &mut mpsc::UnboundedReceiver<Messsage>) -> usize {
+        std::iter::from_fn(|| receiver.try_recv().ok()).count()
+    }
+
+    #[tokio::test]
+    async fn routes_sends_to_groups_and_users() {
+        let manager = DefaultHubLifetimeManager::new();
+        let mut first = connect(&manager, "1", "alice").await;
+        let (second_client, mut second) = client("2", "alice");
+        manager.on_connected(second_client.clone()).await.unwrap();
+        let mut third = connect(&manager, "3", "bob").await;
+        manager.add_to_group("1", "room").await.unwrap();
+        manager.add_to_group("3", "room").await.unwrap();
+        manager.add_to_group("unknown", "room").await.unwrap();
+
+        let room = ClientTarget::Groups {
+            groups: vec!["room".to_owned()],
+            except: vec!["3".to_owned()],
+        };
+        manager.send(room, "Receive", vec![json!("hi")]).await.unwrap();
+        manager.send(ClientTarget::Users(vec!["alice".to_owned()]), "Receive", vec![]).await.unwrap();
+        assert_eq!((received(&mut first), received(&mut second), received(&mut third)), (2, 1, 0));
+
+        manager.remove_from_group("1", "room").await.unwrap();
+        manager.on_disconnected(&second_client).await;
+        manager.send(ClientTarget::All { except: vec![] }, "Receive", vec![]).await.unwrap();
+        let room = ClientTarget::Groups {
+            groups: vec!["room".to_owned()],
+            except: vec![],
+        };
+        manager.send(room, "Receive", vec![]).await.unwrap();
+        assert_eq!((received(&mut first), received(&mut second), received(&mut third)), (1, 0, 2));
+    }
+
+    #[tokio::test]
+    async fn refuses_duplicate_connection_ids() {
+        let manager = DefaultHubLifetimeManager::new();
+        let mut victim = connect(&manager, "1", "alice").await;
+        manager.add_to_group("1", "room").await.unwrap();
+
+        let (intruder, mut intruder_received) = client("1", "mallory");
+        let error = manager.on_connected(intruder.clone()).await.unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::DuplicateConnection);
+        manager.on_disconnected(&intruder).await;
+
+        let room = ClientTarget::Groups {
+            groups: vec!["room".to_owned()],
+            except: vec![],
+        };
+        manager.send(room, "Receive", vec![]).await.unwrap();
+        assert_eq!((received(&mut victim), received(&mut intruder_received)), (1, 0));
+    }
+}
@@ -0,0 +1,543 @@
+//! Hosts hubs in a Rust service for JavaScript, .NET and Rust clients.
+//!
+//! A [`Hub`] is built from named methods and served as an axum [`Router`] answering
+//! `/negotiate` and the WebSocket endpoint, speaking the JSON hub protocol:
+//!
+//! ```no_run
+//! use signalr_rs::server::HubBuilder;
+//!
+//! let hub = HubBuilder::new()
+//!     .with_method("SendMessage", |context, (user, message): (String, String)| async move {
+//!         context.clients().all().send("ReceiveMessage", (user, message)).await
+//!     })
+//!     .build();
+//! let app = axum::Router::new().nest("/chat", hub.router());
+//! ```
+//!
+//! Methods of a connection run one at a time, in the order the client invoked them.
+//! Connections and groups are tracked by a [`HubLifetimeManager`], in memory unless
+//! [`HubBuilder::with_lifetime_manager`] says otherwise; a [`BackplaneHubLifetimeManager`]
+//! shares them between the nodes of a scaled out hub.
+//!
+//! Not supported yet:
+//!
+//! - Streaming. A `StreamInvocation` is answered with an error completion right away, and
+//!   the `StreamItem`s of client-to-server streams are ignored, so hub methods cannot take
+//!   `streamIds` arguments.
+//! - Client results. Invocations the hub sends to clients never carry an `invocationId`,
+//!   so the hub cannot wait for a client to return a value. A `Completion` a client sends
+//!   anyway is ignored.
+
+mod backplane;
+mod clients;
+mod lifetime;
+
+pub use backplane::{Backplane, BackplaneHubLifetimeManager, BackplaneMessage, BackplaneStream, InMemoryBackplane};
+pub use clients::{ClientProxy, Clients, Groups};
+pub use lifetime::{ClientTarget, DefaultHubLifetimeManager, HubClient, HubLifetimeManager};
+
+use std::{
+    collections::{hash_map::RandomState, HashMap},
+    future::Future,
+    hash::{BuildHasher, Hash, Hasher},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        RawQuery, State,
+    },
+    http::{header, HeaderMap, StatusCode, Uri},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Router,
+};
+use futures_util::{
+    future,
+    stream::{SplitSink, SplitStream},
+    SinkExt, StreamExt,
+};
+use serde::Serialize;
+use serde_json::{json, Value};
+use tokio::{sync::mpsc, time};
+
+use crate::{
+    diagnostics::event,
+    error::{Error, ErrorKind},
+    protocol::{
+        self,
+        arguments::FromHubArgs,
+        responses::{CloseFields, CompletionFields, Handshake, Headers, InvocationFields, Messsage, MESSAGE_ENDING_BYTE},
+    },
+    runtime::BoxFuture,
+};
+
+const DEFAULT_KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+const DEFAULT_CLIENT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A connection issued by `/negotiate`, known to clients by its connection token.
+struct Negotiated {
+    connection_id: String,
+    issued: Instant,
+    connected: bool,
+}
+
+type Method = Arc<dyn Fn(HubContext, Vec<Value>) -> BoxFuture<'static, Result<Value, Error>> + Send + Sync>;
+type LifecycleHandler = Arc<dyn Fn(HubContext) -> BoxFuture<'static, ()> + Send + Sync>;
+type UserIdProvider = Arc<dyn Fn(&HeaderMap, &Uri) -> Option<String> + Send + Sync>;
+
+/// What a hub method or lifecycle handler knows about the connection it runs for.
+#[derive(Clone)]
+pub struct HubContext {
+    connection_id: String,
+    user_id: Option<String>,
+    clients: Clients,
+    groups: Groups,
+    headers: Headers,
+}
+
+impl HubContext {
+    /// Id of the calling connection.
+    pub fn connection_id(&self) -> &str {
+        &self.connection_id
+    }
+
+    /// User of the calling connection, see [`HubBuilder::with_user_id_provider`].
+    pub fn user_id(&self) -> Option<&str> {
+        self.user_id.as_deref()
+    }
+
+    pub fn groups(&self) -> &Groups {
+        &self.groups
+    }
+
+    /// The clients of the hub, [`caller`](Clients::caller) being the calling connection.
+    pub fn clients(&self) -> &Clients {
+        &self.clients
+    }
+
+    /// Headers of the invocation being handled, empty in lifecycle handlers.
+    pub fn headers(&self) -> &Headers {
+        &self.headers
+    }
+}
+
+pub struct HubBuilder {
+    methods: HashMap<String, Method>,
+    on_connected: Option<LifecycleHandler>,
+    on_disconnected: Option<LifecycleHandler>,
+    user_id_provider: Option<UserIdProvider>,
+    lifetime_manager: Option<Arc<dyn HubLifetimeManager>>,
+    keep_alive_interval: Duration,
+    client_timeout: Duration,
+}
+
+impl Default for HubBuilder {
+    fn default() -> Self {
+        HubBuilder {
+            methods: HashMap::new(),
+            on_connected: None,
+            on_disconnected: None,
+            user_id_provider: None,
+            lifetime_manager: None,
+            keep_alive_interval: DEFAULT_KEEP_ALIVE_INTERVAL,
+            client_timeout: DEFAULT_CLIENT_TIMEOUT,
+        }
+    }
+}
+
+impl HubBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the hub method clients invoke as `name`, matched case-insensitively.
+    ///
+    /// The arguments are converted to `A` and the value the method resolves to is sent back in
+    /// the completion. Errors built with [`Error::hub_error`] reach the client as they are,
+    /// any other failure is reported as an unexpected error.
+    pub fn with_method<A, R, F, Fut>(mut self, name: &str, method: F) -> HubBuilder
+    where
+        A: FromHubArgs,
+        R: Serialize,
+        F: Fn(HubContext, A) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<R, Error>> + Send + 'static,
+    {
+        let target = name.to_owned();
+        let method = move |context: HubContext, arguments: Vec<Value>| -> BoxFuture<'static, Result<Value, Error>> {
+            let arguments = match A::from_hub_args(&target, arguments) {
+                Ok(arguments) => arguments,
+                Err(error) => return Box::pin(future::ready(Err(error))),
+            };
+            let call = method(context, arguments);
+            Box::pin(async move {
+                let result = call.await?;
+                serde_json::to_value(result).map_err(|e| Error::protocol_error(&e.to_string()))
+            })
+        };
+        self.methods.insert(name.to_lowercase(), Arc::new(method));
+        self
+    }
+
+    /// Runs `handler` once a client completed its handshake, before its first invocation.
+    pub fn on_connected<F, Fut>(self, handler: F) -> HubBuilder
+    where
+        F: Fn(HubContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        HubBuilder {
+            on_connected: Some(Arc::new(move |context| Box::pin(handler(context)))),
+            ..self
+        }
+    }
+
+    /// Runs `handler` once a client is gone, it no longer receives messages.
+    pub fn on_disconnected<F, Fut>(self, handler: F) -> HubBuilder
+    where
+        F: Fn(HubContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        HubBuilder {
+            on_disconnected: Some(Arc::new(move |context| Box::pin(handler(context)))),
+            ..self
+        }
+    }
+
+    /// Identifies the user of a connection from its WebSocket request, for [`Clients::user`].
+    ///
+    /// Browsers cannot set headers on WebSockets, the JavaScript client sends its access token
+    /// as the `access_token` query parameter instead.
+    pub fn with_user_id_provider<F>(self, provider: F) -> HubBuilder
+    where
+        F: Fn(&HeaderMap, &Uri) -> Option<String> + Send + Sync + 'static,
+    {
+        HubBuilder {
+            user_id_provider: Some(Arc::new(provider)),
+            ..self
+        }
+    }
+
+    /// Tracks connections and groups with `manager` instead of a [`DefaultHubLifetimeManager`].
+    pub fn with_lifetime_manager<M: HubLifetimeManager>(self, manager: M) -> HubBuilder {
+        HubBuilder {
+            lifetime_manager: Some(Arc::new(manager)),
+            ..self
+        }
+    }
+
+    /// How long the hub waits without sending anything to a client before it sends a ping.
+    pub fn with_keep_alive_interval(self, keep_alive_interval: Duration) -> HubBuilder {
+        HubBuilder { keep_alive_interval, ..self }
+    }
+
+    /// How long the hub waits without receiving anything from a client before it drops it.
+    pub fn with_client_timeout(self, client_timeout: Duration) -> HubBuilder {
+        HubBuilder { client_timeout, ..self }
+    }
+
+    pub fn build(self) -> Hub {
+        Hub {
+            inner: Arc::new(HubInner {
+                methods: self.methods,
+                on_connected: self.on_connected,
+                on_disconnected: self.on_disconnected,
+                user_id_provider: self.user_id_provider,
+                keep_alive_interval: self.keep_alive_interval,
+                client_timeout: self.client_timeout,
+                manager: self
+                    .lifetime_manager
+                    .unwrap_or_else(|| Arc::new(DefaultHubLifetimeManager::new())),
+                next_connection: AtomicU64::new(0),
+                id_seed: RandomState::new(),
+                negotiated: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+}
+
+/// A hub ready to be served, cheap to clone.
+#[derive(Clone)]
+pub struct Hub {
+    inner: Arc<HubInner>,
+}
+
+struct HubInner {
+    methods: HashMap<String, Method>,
+    on_connected: Option<LifecycleHandler>,
+    on_disconnected: Option<LifecycleHandler>,
+    user_id_provider: Option<UserIdProvider>,
+    keep_alive_interval: Duration,
+    client_timeout: Duration,
+    manager: Arc<dyn HubLifetimeManager>,
+    next_connection: AtomicU64,
+    id_seed: RandomState,
+    /// Connections issued by `/negotiate`, by connection token.
+    negotiated: Mutex<HashMap<String, Negotiated>>,
+}
+
+impl Hub {
+    /// The clients of the hub, to send to them from outside of hub methods.
+    ///
+    /// There is no caller, so [`Clients::caller`] reaches nobody and [`Clients::others`] everybody.
+    pub fn clients(&self) -> Clients {
+        Clients::new(self.inner.manager.clone(), None)
+    }
+
+    pub fn groups(&self) -> Groups {
+        Groups::new(self.inner.manager.clone())
+    }
+
+    /// Routes serving the hub, to be nested at the hub url.
+    pub fn router(&self) -> Router {
+        Router::new()
+            .route("/", get(connect))
+            .route("/negotiate", post(negotiate))
+            .with_state(self.inner.clone())
+    }
+}
+
+impl HubInner {
+    /// A new unguessable id, for connection ids and connection tokens alike.
+    fn new_id(&self) -> String {
+        let mut hasher = self.id_seed.build_hasher();
+        self.next_connection.fetch_add(1, Ordering::Relaxed).hash(&mut hasher);
+        let high = hasher.finish();
+        high.hash(&mut hasher);
+        format!("{:016x}{:016x}", high, hasher.finish())
+    }
+
+    /// Issues a connection and the token its client connects with.
+    fn negotiate(&self) -> (String, String) {
+        let (connection_id, token) = (self.new_id(), self.new_id());
+        let mut negotiated = self.negotiated.lock().unwrap();
+        // Tokens not used within the client timeout expire.
+        negotiated.retain(|_, connection| connection.connected || connection.issued.elapsed() < self.client_timeout);
+        negotiated.insert(
+            token.clone(),
+            Negotiated {
+                connection_id: connection_id.clone(),
+                issued: Instant::now(),
+                connected: false,
+            },
+        );
+        (connection_id, token)
+    }
+
+    /// The connection id `token` was issued for, unless it is unknown or already connected.
+    fn claim(&self, token: &str) -> Result<String, StatusCode> {
+        let mut negotiated = self.negotiated.lock().unwrap();
+        match negotiated.get_mut(token) {
+            None => Err(StatusCode::NOT_FOUND),
+            Some(connection) if connection.connected => Err(StatusCode::CONFLICT),
+            Some(connection) => {
+                connection.connected = true;
+                Ok(connection.connection_id.clone())
+            }
+        }
+    }
+
+    fn context(&self, client: &HubClient) -> HubContext {
+        HubContext {
+            connection_id: client.connection_id().to_owned(),
+            user_id: client.user_id().map(str::to_owned),
+            clients: Clients::new(self.manager.clone(), Some(client.connection_id().to_owned())),
+            groups: Groups::new(self.manager.clone()),
+            headers: Headers::new(),
+        }
+    }
+
+    /// Runs the invoked method, returning the completion to send back if the client expects one.
+    async fn invoke(&self, client: &HubClient, fields: InvocationFields) -> Option<Messsage> {
+        let InvocationFields {
+            headers,
+            invocation_id,
+            target,
+            arguments,
+            ..
+        } = fields;
+        let result = match self.methods.get(&target.to_lowercase()) {
+            Some(method) => method(HubContext { headers, ..self.context(client) }, arguments).await,
+            None => Err(Error::hub_error(&format!("Unknown hub method '{}'", target))),
+        };
+        let invocation_id = invocation_id?;
+        Some(Messsage::Completion(match result {
+            Ok(result) => CompletionFields::completed(&invocation_id, result),
+            Err(error) if error.kind() == ErrorKind::Hub => CompletionFields::failed(&invocation_id, error.message()),
+            Err(_) => CompletionFields::failed(
+                &invocation_id,
+                &format!("An unexpected error occurred invoking '{}' on the server.", target),
+            ),
+        }))
+    }
+}
+
+async fn negotiate(State(inner): State<Arc<HubInner>>) -> impl IntoResponse {
+    let (connection_id, token) = inner.negotiate();
+    let body = json!({
+        "negotiateVersion": 1,
+        "connectionId": connection_id,
+        "connectionToken": token,
+        "availableTransports": [{ "transport": "WebSockets", "transferFormats": ["Text"] }],
+    });
+    ([(header::CONTENT_TYPE, "application/json")], body.to_string())
+}
+
+async fn connect(
+    State(inner): State<Arc<HubInner>>,
+    RawQuery(query): RawQuery,
+    headers: HeaderMap,
+    uri: Uri,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    let token = query.as_deref().and_then(|query| {
+        form_urlencoded::parse(query.as_bytes())
+            .find(|(name, _)| name == "id")
+            .map(|(_, token)| token.into_owned())
+    });
+    // Clients that skip negotiation connect without a token.
+    let (connection_id, claim) = match token {
+        None => (inner.new_id(), None),
+        Some(token) => match inner.claim(&token) {
+            Ok(connection_id) => (
+                connection_id,
+                Some(Claim {
+                    inner: inner.clone(),
+                    token,
+                }),
+            ),
+            Err(status) => return status.into_response(),
+        },
+    };
+    let user_id = inner.user_id_provider.as_ref().and_then(|provider| provider(&headers, &uri));
+    upgrade.on_upgrade(move |socket| async move {
+        run_connection(inner, socket, connection_id, user_id).await;
+        drop(claim);
+    })
+}
+
+/// Forgets a connection token once its connection is gone, or if the upgrade never happens.
+struct Claim {
+    inner: Arc<HubInner>,
+    token: String,
+}
+
+impl Drop for Claim {
+    fn drop(&mut self) {
+        self.inner.negotiated.lock().unwrap().remove(&self.token);
+    }
+}
+
+async fn run_connection(inner: Arc<HubInner>, socket: WebSocket, connection_id: String, user_id: Option<String>) {
+    let (mut sink, mut stream) = socket.split();
+    let buffered = match handshake(&mut sink, &mut stream, inner.client_timeout).await {
+        Some(buffered) => buffered,
+        None => return,
+    };
+
+    let (outbound, outbound_rx) = mpsc::unbounded_channel();
+    let client = HubClient::new(connection_id, user_id, outbound.clone());
+    if let Err(error) = inner.manager.on_connected(client.clone()).await {
+        event!(warn, connection_id = %client.connection_id(), %error, "refused a connection");
+        let close = Messsage::Close(CloseFields::new(Some(error.message().to_owned()), false));
+        if let Some(text) = close.serialize() {
+            let _ = sink.send(Message::Text(text)).await;
+        }
+        let _ = sink.close().await;
+        return;
+    }
+    let writer = tokio::spawn(write_loop(sink, outbound_rx, inner.keep_alive_interval));
+    if let Some(on_connected) = &inner.on_connected {
+        on_connected(inner.context(&client)).await;
+    }
+
+    let mut pending = buffered;
+    'read: loop {
+        for message in pending.drain(..) {
+            let completion = match message {
+                Messsage::Invocation(fields) => {
+                    inner.invoke(&client, fields).await
+                }
+                Messsage::StreamInvocation(fields) => Some(Messsage::Completion(CompletionFields::failed(
+                    &fields.invocation_id,
+                    "Streaming hub methods are not supported",
+                ))),
+                Messsage::Close(_) => break 'read,
+                _ => None,
+            };
+            if let Some(completion) = completion {
+                let _ = outbound.send(completion);
+            }
+        }
+
+        match time::timeout(inner.client_timeout, stream.next()).await {
+            Ok(Some(Ok(Message::Text(text)))) => pending.extend(protocol::parse_messages(&text)),
+            Ok(Some(Ok(Message::Close(_)))) | Ok(Some(Err(_))) | Ok(None) | Err(_) => break,
+            Ok(Some(Ok(_))) => {}
+        }
+    }
+
+    inner.manager.on_disconnected(&client).await;
+    if let Some(on_disconnected) = &inner.on_disconnected {
+        on_disconnected(inner.context(&client)).await;
+    }
+    // The writer drains what was queued, then closes the socket.
+    drop((outbound, client));
+    let _ = writer.await;
+}
+
+/// Answers the handshake request, returning the messages sent along with it.
+async fn handshake(
+    sink: &mut SplitSink<WebSocket, Message>,
+    stream: &mut SplitStream<WebSocket>,
+    timeout: Duration,
+) -> Option<Vec<Messsage>> {
+    let payload = loop {
+        match time::timeout(timeout, stream.next()).await.ok()?? {
+            Ok(Message::Text(text)) => break text,
+            Ok(Message::Close(_)) | Err(_) => return None,
+            Ok(_) => continue,
+        }
+    };
+
+    let mut records = protocol::split_records(&payload);
+    let error = match records.next().map(serde_json::from_str::<Handshake>) {
+        Some(Ok(Handshake::Request { protocol, .. })) if protocol == "json" => None,
+        Some(Ok(Handshake::Request { protocol, .. })) => {
+            Some(format!("The protocol '{}' is not supported.", protocol))
+        }
+        _ => Some("Handshake request is invalid.".to_owned()),
+    };
+    let accepted = error.is_none();
+    let response = serde_json::to_string(&Handshake::Response { error }).unwrap() + MESSAGE_ENDING_BYTE;
+    sink.send(Message::Text(response)).await.ok()?;
+    if !accepted {
+        let _ = sink.close().await;
+        return None;
+    }
+    Some(records.filter_map(Messsage::deserialize).collect())
+}
+
+async fn write_loop(
+    mut sink: SplitSink<WebSocket, Message>,
+    mut outbound: mpsc::UnboundedReceiver<Messsage>,
+    keep_alive_interval: Duration,
+) {
+    loop {
+        let message = match time::timeout(keep_alive_interval, outbound.recv()).await {
+            Ok(Some(message)) => message,
+            Ok(None) => break,
+            Err(_) => Messsage::Ping,
+        };
+        if let Some(text) = message.serialize() {
+            if sink.send(Message::Text(text)).await.is_err() {
+                return;
+            }
+        }
+    }
+    let _ = sink.close().await;
+}
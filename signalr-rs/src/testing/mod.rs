@@ -0,0 +1,403 @@
+//! An in-process SignalR server for testing clients without an ASP.NET Core hub.
+//!
+//! [`MockHubServer`] listens on a local port, answers `/negotiate`, accepts the WebSocket and
+//! completes the handshake; every connected client becomes a [`MockSession`] the test scripts:
+//!
+//! ```no_run
+//! # async fn example() -> std::io::Result<()> {
+//! use serde_json::json;
+//! use signalr_rs::{testing::MockHubServer, HubConnectionBuilder};
+//!
+//! let mut server = MockHubServer::start().await?;
+//! let connection = HubConnectionBuilder::new().with_url(server.url()).build().unwrap();
+//! let (started, mut session) = tokio::join!(connection.start(), server.accept());
+//! started.unwrap();
+//!
+//! let (result, ()) = tokio::join!(connection.invoke("Add", (1, 2)), async {
+//!     let invocation = session.expect_invocation("Add").await;
+//!     session.complete(&invocation, json!(3));
+//! });
+//! assert_eq!(result.unwrap(), json!(3));
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The `expect_*` methods panic when the client sends something else or nothing within
+//! [`EXPECT_TIMEOUT`], which fails the test.
+
+use std::{
+    io,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use futures_util::{
+    future::{self, Either},
+    SinkExt, StreamExt,
+};
+use serde_json::Value;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::{mpsc, oneshot},
+    task::JoinHandle,
+    time,
+};
+use tokio_tungstenite::{
+    tungstenite::{
+        handshake::server::{Request, Response},
+        Message,
+    },
+    WebSocketStream,
+};
+
+use crate::protocol::{
+    self,
+    arguments::IntoHubArgs,
+    responses::{
+        CloseFields, CompletionFields, Handshake, Headers, InvocationFields, Messsage, StreamItemFields,
+        MESSAGE_ENDING_BYTE,
+    },
+};
+
+/// How long the `expect_*` methods wait before failing the test.
+pub const EXPECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A local server speaking the JSON hub protocol, see the [module documentation](self).
+pub struct MockHubServer {
+    address: SocketAddr,
+    sessions: mpsc::UnboundedReceiver<MockSession>,
+    listener: JoinHandle<()>,
+}
+
+impl MockHubServer {
+    /// Binds an ephemeral port on the loopback interface and starts accepting clients.
+    pub async fn start() -> io::Result<MockHubServer> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?;
+        let (sessions_tx, sessions) = mpsc::unbounded_channel();
+        let listener = tokio::spawn(listen(listener, sessions_tx));
+        Ok(MockHubServer {
+            address,
+            sessions,
+            listener,
+        })
+    }
+
+    /// Url of the hub to give to [`HubConnectionBuilder::with_url`](crate::HubConnectionBuilder::with_url).
+    pub fn url(&self) -> String {
+        format!("http://{}/hub", self.address)
+    }
+
+    /// Waits for the next client to complete the handshake.
+    pub async fn accept(&mut self) -> MockSession {
+        match time::timeout(EXPECT_TIMEOUT, self.sessions.recv()).await {
+            Ok(Some(session)) => session,
+            _ => panic!("no client connected to the mock hub"),
+        }
+    }
+}
+
+impl Drop for MockHubServer {
+    fn drop(&mut self) {
+        self.listener.abort();
+    }
+}
+
+/// An invocation received from the client.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MockInvocation {
+    pub invocation_id: Option<String>,
+    pub target: String,
+    pub arguments: Vec<Value>,
+    pub headers: Headers,
+}
+
+impl MockInvocation {
+    fn id(&self) -> &str {
+        self.invocation_id.as_deref().expect("the client expects no completion for this invocation")
+    }
+}
+
+enum Command {
+    Send(Messsage),
+    Drop,
+}
+
+/// One connected client, dropping it closes the WebSocket.
+pub struct MockSession {
+    connection_id: String,
+    incoming: mpsc::UnboundedReceiver<Messsage>,
+    commands: mpsc::UnboundedSender<Command>,
+    close_code: Option<oneshot::Receiver<Option<u16>>>,
+}
+
+impl MockSession {
+    /// Id the client negotiated, as sent back on the WebSocket url.
+    pub fn connection_id(&self) -> &str {
+        &self.connection_id
+    }
+
+    /// Next message from the client, pings skipped; `None` once the client disconnected.
+    pub async fn next_message(&mut self) -> Option<Messsage> {
+        match time::timeout(EXPECT_TIMEOUT, self.incoming.recv()).await {
+            Ok(message) => message,
+            Err(_) => panic!("the client sent nothing to the mock hub"),
+        }
+    }
+
+    /// Expects an Invocation of `target`.
+    pub async fn expect_invocation(&mut self, target: &str) -> MockInvocation {
+        match self.next_message().await {
+            Some(Messsage::Invocation(fields)) if fields.target == target => MockInvocation {
+                invocation_id: fields.invocation_id,
+                target: fields.target,
+                arguments: fields.arguments,
+                headers: fields.headers,
+            },
+            other => panic!("expected an invocation of {}, got {:?}", target, other),
+        }
+    }
+
+    /// Expects a StreamInvocation of `target`.
+    pub async fn expect_stream_invocation(&mut self, target: &str) -> MockInvocation {
+        match self.next_message().await {
+            Some(Messsage::StreamInvocation(fields)) if fields.target == target => MockInvocation {
+                invocation_id: Some(fields.invocation_id),
+                target: fields.target,
+                arguments: fields.arguments,
+                headers: fields.headers,
+            },
+            other => panic!("expected a stream invocation of {}, got {:?}", target, other),
+        }
+    }
+
+    /// Expects a CancelInvocation of `invocation`.
+    pub async fn expect_cancel_invocation(&mut self, invocation: &MockInvocation) {
+        match self.next_message().await {
+            Some(Messsage::CancelInvokation(fields)) if Some(fields.invocation_id()) == invocation.invocation_id.as_deref() => {}
+            other => panic!("expected {:?} to be cancelled, got {:?}", invocation.invocation_id, other),
+        }
+    }
+
+    /// Expects the client to send a Close message or to disconnect.
+    pub async fn expect_close(&mut self) {
+        match self.next_message().await {
+            Some(Messsage::Close(_)) | None => {}
+            other => panic!("expected the client to close, got {:?}", other),
+        }
+    }
+
+    /// Expects the client to close the WebSocket, returning the status code of its close frame.
+    pub async fn expect_close_frame(&mut self) -> Option<u16> {
+        let close_code = self.close_code.take().expect("the close frame was already expected");
+        match time::timeout(EXPECT_TIMEOUT, close_code).await {
+            Ok(Ok(code)) => code,
+            Ok(Err(_)) => panic!("the client went away without a close frame"),
+            Err(_) => panic!("the client did not close the WebSocket"),
+        }
+    }
+
+    /// Sends any message to the client.
+    pub fn send(&self, message: Messsage) {
+        let _ = self.commands.send(Command::Send(message));
+    }
+
+    /// Completes `invocation` with `result`.
+    pub fn complete(&self, invocation: &MockInvocation, result: Value) {
+        self.send(Messsage::Completion(CompletionFields::completed(invocation.id(), result)));
+    }
+
+    /// Fails `invocation` with `error`.
+    pub fn complete_with_error(&self, invocation: &MockInvocation, error: &str) {
+        self.send(Messsage::Completion(CompletionFields::failed(invocation.id(), error)));
+    }
+
+    /// Pushes one item of the stream started by `invocation`.
+    pub fn stream_item(&self, invocation: &MockInvocation, item: Value) {
+        self.send(Messsage::StreamItem(StreamItemFields::new(invocation.id(), item)));
+    }
+
+    /// Invokes `target` on the client.
+    pub fn invoke<A: IntoHubArgs>(&self, target: &str, arguments: A) {
+        let arguments = arguments.into_hub_args().expect("arguments must serialize");
+        self.send(Messsage::Invocation(InvocationFields::new(None, target, arguments)));
+    }
+
+    /// Sends a Close message, the server side of the WebSocket stays open until the client closes it.
+    pub fn close(&self, error: Option<&str>, allow_reconnect: bool) {
+        self.send(Messsage::Close(CloseFields::new(error.map(str::to_owned), allow_reconnect)));
+    }
+
+    /// Drops the TCP connection without a Close message or WebSocket close frame.
+    pub fn drop_connection(self) {
+        let _ = self.commands.send(Command::Drop);
+    }
+}
+
+async fn listen(listener: TcpListener, sessions: mpsc::UnboundedSender<MockSession>) {
+    let next_connection = Arc::new(AtomicU64::new(0));
+    while let Ok((stream, _)) = listener.accept().await {
+        let sessions = sessions.clone();
+        let next_connection = next_connection.clone();
+        tokio::spawn(async move {
+            if is_negotiate(&stream).await {
+                let id = format!("mock-connection-{}", next_connection.fetch_add(1, Ordering::Relaxed));
+                let _ = negotiate(stream, &id).await;
+            } else if let Some(session) = accept_session(stream).await {
+                let _ = sessions.send(session);
+            }
+        });
+    }
+}
+
+/// Peeks at the request line without consuming it, the WebSocket upgrade reads it again.
+async fn is_negotiate(stream: &TcpStream) -> bool {
+    let mut buffer = [0; 512];
+    loop {
+        let read = match stream.peek(&mut buffer).await {
+            Ok(0) | Err(_) => return false,
+            Ok(read) => read,
+        };
+        let head = String::from_utf8_lossy(&buffer[..read]);
+        if let Some((line, _)) = head.split_once("\r\n") {
+            return line.split(' ').nth(1).is_some_and(|path| path.contains("/negotiate"));
+        }
+        if read == buffer.len() {
+            return false;
+        }
+        tokio::task::yield_now().await;
+    }
+}
+
+async fn negotiate(mut stream: TcpStream, id: &str) -> io::Result<()> {
+    // The client posts an empty body, the request ends with its headers.
+    let mut request = Vec::new();
+    let mut buffer = [0; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 {
+            return Ok(());
+        }
+        request.extend_from_slice(&buffer[..read]);
+    }
+
+    let body = serde_json::json!({
+        "negotiateVersion": 1,
+        "connectionId": id,
+        "connectionToken": id,
+        "availableTransports": [{ "transport": "WebSockets", "transferFormats": ["Text", "Binary"] }],
+    })
+    .to_string();
+    let response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+async fn accept_session(stream: TcpStream) -> Option<MockSession> {
+    let mut connection_id = String::new();
+    // The signature is imposed by tungstenite.
+    #[allow(clippy::result_large_err)]
+    let callback = |request: &Request, response: Response| {
+        connection_id = request
+            .uri()
+            .query()
+            .and_then(|query| query.split('&').find_map(|pair| pair.strip_prefix("id=")))
+            .unwrap_or_default()
+            .to_owned();
+        Ok(response)
+    };
+    let mut socket = tokio_tungstenite::accept_hdr_async(stream, callback).await.ok()?;
+
+    let buffered = loop {
+        match socket.next().await? {
+            Ok(Message::Text(text)) => break handshake(&mut socket, &text).await?,
+            Ok(Message::Close(_)) | Err(_) => return None,
+            Ok(_) => continue,
+        }
+    };
+
+    let (incoming_tx, incoming) = mpsc::unbounded_channel();
+    let (commands, commands_rx) = mpsc::unbounded_channel();
+    for message in buffered {
+        let _ = incoming_tx.send(message);
+    }
+    let (close_code_tx, close_code) = oneshot::channel();
+    tokio::spawn(serve(socket, incoming_tx, commands_rx, close_code_tx));
+    Some(MockSession {
+        connection_id,
+        incoming,
+        commands,
+        close_code: Some(close_code),
+    })
+}
+
+/// Answers the handshake request, returning the messages sent along with it.
+async fn handshake(socket: &mut WebSocketStream<TcpStream>, payload: &str) -> Option<Vec<Messsage>> {
+    let mut records = protocol::split_records(payload);
+    let accepted = matches!(
+        records.next().map(serde_json::from_str::<Handshake>),
+        Some(Ok(Handshake::Request { ref protocol, .. })) if protocol == "json"
+    );
+    let response = Handshake::Response {
+        error: (!accepted).then(|| "Only the json protocol is supported by the mock hub".to_owned()),
+    };
+    let response = serde_json::to_string(&response).unwrap() + MESSAGE_ENDING_BYTE;
+    socket.send(Message::Text(response)).await.ok()?;
+    if !accepted {
+        return None;
+    }
+    Some(records.filter_map(Messsage::deserialize).filter(|message| !matches!(message, Messsage::Ping)).collect())
+}
+
+async fn serve(
+    mut socket: WebSocketStream<TcpStream>,
+    incoming: mpsc::UnboundedSender<Messsage>,
+    mut commands: mpsc::UnboundedReceiver<Command>,
+    close_code: oneshot::Sender<Option<u16>>,
+) {
+    loop {
+        let event = match future::select(Box::pin(commands.recv()), socket.next()).await {
+            Either::Left((command, _)) => Either::Left(command),
+            Either::Right((frame, _)) => Either::Right(frame),
+        };
+        match event {
+            Either::Left(Some(Command::Send(message))) => {
+                let sent = match message.serialize() {
+                    Some(text) => socket.send(Message::Text(text)).await.is_ok(),
+                    None => true,
+                };
+                if !sent {
+                    return;
+                }
+            }
+            Either::Left(Some(Command::Drop)) => return,
+            Either::Left(None) => {
+                let _ = socket.close(None).await;
+                return;
+            }
+            Either::Right(Some(Ok(Message::Text(text)))) => {
+                for message in protocol::parse_messages(&text) {
+                    if !matches!(message, Messsage::Ping) {
+                        let _ = incoming.send(message);
+                    }
+                }
+            }
+            Either::Right(Some(Ok(Message::Close(frame)))) => {
+                let _ = close_code.send(frame.map(|frame| frame.code.into()));
+                let _ = socket.close(None).await;
+                return;
+            }
+            Either::Right(Some(Err(_))) | Either::Right(None) => return,
+            Either::Right(Some(Ok(_))) => {}
+        }
+    }
+}
@@ -0,0 +1,190 @@
+//! W3C trace context carried in the headers of hub messages, see the `tracing` feature.
+//!
+//! Every `send`, `invoke` and `stream` runs in a span whose context is injected as the
+//! `traceparent` and `tracestate` headers of the invocation, a server in the same trace (the
+//! .NET one included) picks them up as the parent of its own activity. Invocations from the
+//! server are handled in a child span of the context they carry, calls made from the handler
+//! continue that trace.
+
+use std::{
+    cell::RefCell,
+    collections::hash_map::RandomState,
+    hash::{BuildHasher, Hash, Hasher},
+    sync::atomic::{AtomicU64, Ordering},
+    time::SystemTime,
+};
+
+use crate::protocol::responses::Headers;
+
+pub const TRACEPARENT: &str = "traceparent";
+pub const TRACESTATE: &str = "tracestate";
+
+const VERSION: &str = "00";
+const SAMPLED: u8 = 0x01;
+
+thread_local! {
+    static CURRENT: RefCell<Option<TraceContext>> = const { RefCell::new(None) };
+}
+
+/// The position of an operation in a distributed trace.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TraceContext {
+    trace_id: u128,
+    span_id: u64,
+    flags: u8,
+    state: Option<String>,
+}
+
+impl TraceContext {
+    /// Starts a new, sampled trace.
+    pub fn new_root() -> Self {
+        TraceContext {
+            trace_id: (u128::from(random_id()) << 64) | u128::from(random_id()),
+            span_id: random_id(),
+            flags: SAMPLED,
+            state: None,
+        }
+    }
+
+    /// An operation of the same trace, caused by this one.
+    pub fn child(&self) -> Self {
+        TraceContext {
+            span_id: random_id(),
+            ..self.clone()
+        }
+    }
+
+    /// Parses a `traceparent` header and its `tracestate`, `None` if `traceparent` is invalid.
+    pub fn parse(traceparent: &str, tracestate: Option<&str>) -> Option<Self> {
+        let mut fields = traceparent.split('-');
+        let version = fields.next().filter(|version| is_hex(version, 2) && *version != "ff")?;
+        let trace_id = fields.next().filter(|id| is_hex(id, 32))?;
+        let span_id = fields.next().filter(|id| is_hex(id, 16))?;
+        let flags = fields.next().filter(|flags| is_hex(flags, 2))?;
+        // Later versions may append fields, version 00 has exactly four.
+        if version == VERSION && fields.next().is_some() {
+            return None;
+        }
+        let context = TraceContext {
+            trace_id: u128::from_str_radix(trace_id, 16).ok()?,
+            span_id: u64::from_str_radix(span_id, 16).ok()?,
+            flags: u8::from_str_radix(flags, 16).ok()?,
+            state: tracestate.map(str::trim).filter(|state| !state.is_empty()).map(str::to_owned),
+        };
+        (context.trace_id != 0 && context.span_id != 0).then_some(context)
+    }
+
+    /// Reads the context from the headers of a message.
+    pub fn extract(headers: &Headers) -> Option<Self> {
+        let traceparent = headers.get(TRACEPARENT)?;
+        Self::parse(traceparent, headers.get(TRACESTATE).map(String::as_str))
+    }
+
+    /// Writes the context to the headers of a message, replacing any previous one.
+    pub fn inject(&self, headers: &mut Headers) {
+        headers.insert(TRACEPARENT.to_owned(), self.traceparent());
+        match &self.state {
+            Some(state) => headers.insert(TRACESTATE.to_owned(), state.clone()),
+            None => headers.remove(TRACESTATE),
+        };
+    }
+
+    /// The context of the handler running on this thread, if any.
+    ///
+    /// Handlers that move work to another task capture it there and pass it along with
+    /// [`inject`](TraceContext::inject) into [`InvocationOptions`](crate::InvocationOptions).
+    pub fn current() -> Option<Self> {
+        CURRENT.with(|current| current.borrow().clone())
+    }
+
+    pub fn traceparent(&self) -> String {
+        format!("{}-{:032x}-{:016x}-{:02x}", VERSION, self.trace_id, self.span_id, self.flags)
+    }
+
+    pub fn tracestate(&self) -> Option<&str> {
+        self.state.as_deref()
+    }
+
+    /// The trace id, as 32 lowercase hex digits.
+    pub fn trace_id(&self) -> String {
+        format!("{:032x}", self.trace_id)
+    }
+
+    /// The id of this operation, as 16 lowercase hex digits.
+    pub fn span_id(&self) -> String {
+        format!("{:016x}", self.span_id)
+    }
+
+    pub fn sampled(&self) -> bool {
+        self.flags & SAMPLED != 0
+    }
+
+    /// Makes this context the [`current`](TraceContext::current) one until the guard is dropped.
+    pub(crate) fn enter(self) -> CurrentGuard {
+        let previous = CURRENT.with(|current| current.replace(Some(self)));
+        CurrentGuard { previous }
+    }
+}
+
+/// Restores the previous current context on drop.
+pub(crate) struct CurrentGuard {
+    previous: Option<TraceContext>,
+}
+
+impl Drop for CurrentGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        CURRENT.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+fn is_hex(field: &str, length: usize) -> bool {
+    field.len() == length && field.bytes().all(|byte| matches!(byte, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+fn random_id() -> u64 {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    loop {
+        let mut hasher = RandomState::new().build_hasher();
+        SystemTime::now().hash(&mut hasher);
+        COUNTER.fetch_add(1, Ordering::Relaxed).hash(&mut hasher);
+        let id = hasher.finish();
+        if id != 0 {
+            return id;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TraceContext, TRACEPARENT, TRACESTATE};
+    use crate::protocol::responses::Headers;
+
+    #[test]
+    fn parses_and_injects_w3c_headers() {
+        let traceparent = "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01";
+        let context = TraceContext::parse(traceparent, Some("congo=t61rcWkgMzE")).unwrap();
+        assert_eq!(context.trace_id(), "0af7651916cd43dd8448eb211c80319c");
+        assert!(context.sampled());
+
+        let child = context.child();
+        assert_eq!(child.trace_id(), context.trace_id());
+        assert_ne!(child.span_id(), context.span_id());
+        let mut headers = Headers::new();
+        child.inject(&mut headers);
+        assert_eq!(headers[TRACESTATE], "congo=t61rcWkgMzE");
+        assert_eq!(TraceContext::extract(&headers), Some(child));
+
+        for invalid in [
+            "00-00000000000000000000000000000000-b7ad6b7169203331-01",
+            "00-0af7651916cd43dd8448eb211c80319c-0000000000000000-01",
+            "ff-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01",
+            "00-0AF7651916CD43DD8448EB211C80319C-b7ad6b7169203331-01",
+            "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01-extra",
+        ] {
+            assert_eq!(TraceContext::parse(invalid, None), None, "{}", invalid);
+        }
+        headers.insert(TRACEPARENT.to_owned(), "garbage".to_owned());
+        assert_eq!(TraceContext::extract(&headers), None);
+    }
+}
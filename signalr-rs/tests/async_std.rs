@@ -0,0 +1,39 @@
+use serde_json::json;
+use signalr_rs::{
+    runtime::{AsyncStdRuntime, AsyncStdTransport},
+    testing::MockHubServer,
+    HubConnectionBuilder,
+};
+
+/// The mock hub needs tokio, the client only ever runs on async-std tasks.
+#[tokio::test(flavor = "multi_thread")]
+async fn connects_and_invokes_on_async_std() {
+    let mut server = MockHubServer::start().await.unwrap();
+    let connection = HubConnectionBuilder::new()
+        .with_url(server.url())
+        .with_runtime(AsyncStdRuntime)
+        .with_transport(AsyncStdTransport::new())
+        .build()
+        .unwrap();
+
+    let started = async_std::task::spawn({
+        let connection = connection.clone();
+        async move { connection.start().await }
+    });
+    let (started, mut session) = tokio::join!(started, server.accept());
+    started.unwrap();
+
+    let invoked = async_std::task::spawn({
+        let connection = connection.clone();
+        async move { connection.invoke("Add", (1, 2)).await }
+    });
+    let (result, ()) = tokio::join!(invoked, async {
+        let invocation = session.expect_invocation("Add").await;
+        session.complete(&invocation, json!(3));
+    });
+    assert_eq!(result.unwrap(), json!(3));
+
+    let stopped = async_std::task::spawn(async move { connection.stop().await });
+    let (stopped, ()) = tokio::join!(stopped, session.expect_close());
+    stopped.unwrap();
+}
@@ -0,0 +1,105 @@
+use std::{
+    sync::{mpsc, Arc, Mutex},
+    time::Duration,
+};
+
+use serde_json::json;
+use signalr_rs::{blocking::HubConnection, error::ErrorKind, protocol::responses::Messsage, testing::MockHubServer};
+use tokio::task;
+
+/// Runs `call` off the runtime, the mock hub keeps being served meanwhile.
+async fn blocking<T, F>(connection: &Arc<HubConnection>, call: F) -> T
+where
+    T: Send + 'static,
+    F: FnOnce(&HubConnection) -> T + Send + 'static,
+{
+    let connection = connection.clone();
+    task::spawn_blocking(move || call(&connection)).await.unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn blocking_connections_talk_to_the_mock_hub() {
+    let mut server = MockHubServer::start().await.unwrap();
+    let url = server.url();
+    let (connection, mut session) = tokio::join!(task::spawn_blocking(move || HubConnection::connect(&url)), server.accept());
+    let connection = Arc::new(connection.unwrap().unwrap());
+    assert_eq!(connection.connection_id(), session.connection_id());
+
+    let (result, ()) = tokio::join!(blocking(&connection, |connection| connection.invoke("Add", (1, 2))), async {
+        let invocation = session.expect_invocation("Add").await;
+        assert_eq!(invocation.arguments, vec![json!(1), json!(2)]);
+        session.complete(&invocation, json!(3));
+    });
+    assert_eq!(result.unwrap(), json!(3));
+
+    let (result, ()) = tokio::join!(blocking(&connection, |connection| connection.invoke("Fail", ())), async {
+        let invocation = session.expect_invocation("Fail").await;
+        session.complete_with_error(&invocation, "nope");
+    });
+    let error = result.unwrap_err();
+    assert_eq!((error.kind(), error.message()), (ErrorKind::Hub, "nope"));
+
+    connection.send("Notify", ("hello",)).unwrap();
+    let invocation = session.expect_invocation("Notify").await;
+    assert_eq!((invocation.invocation_id, invocation.arguments), (None, vec![json!("hello")]));
+
+    let (sender, receiver) = mpsc::channel();
+    let subscribe = |name: &'static str| {
+        let sender = sender.clone();
+        connection.on("receive", move |(message,): (String,)| {
+            let _ = sender.send(format!("{} {}", name, message));
+        })
+    };
+    subscribe("first").detach();
+    let second = subscribe("second");
+    let errors = sender.clone();
+    connection.on_handler_error(move |error| {
+        let _ = errors.send(format!("{:?}", error.kind()));
+    });
+    // Targets match regardless of case, each handler sees every call.
+    session.invoke("Receive", ("handled",));
+    session.invoke("RECEIVE", (1,));
+    session.invoke("Other", ("unhandled",));
+    let received = task::spawn_blocking(move || {
+        let received: Vec<_> = (0..4).map(|_| receiver.recv_timeout(Duration::from_secs(5)).unwrap()).collect();
+        (received, receiver)
+    });
+    let (received, receiver) = received.await.unwrap();
+    assert_eq!(received, vec!["first handled", "second handled", "InvalidArguments", "InvalidArguments"]);
+    drop(second);
+    let message = blocking(&connection, |connection| connection.messages().next()).await;
+    match message {
+        Some(Messsage::Invocation(fields)) => {
+            assert_eq!((fields.target(), fields.arguments()), ("Other", &[json!("unhandled")][..]));
+        }
+        other => panic!("unexpected message {:?}", other),
+    }
+
+    session.invoke("Receive", ("again",));
+    let received = task::spawn_blocking(move || receiver.recv_timeout(Duration::from_secs(5)));
+    assert_eq!(received.await.unwrap().unwrap(), "first again");
+
+    let ((), ()) = tokio::join!(blocking(&connection, |connection| connection.stop()), session.expect_close());
+    let error = connection.send("Notify", ()).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::ConnectionClosed);
+    assert!(blocking(&connection, |connection| connection.messages().next()).await.is_none());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn a_handler_can_drop_the_last_handle() {
+    let mut server = MockHubServer::start().await.unwrap();
+    let url = server.url();
+    let (connection, mut session) = tokio::join!(task::spawn_blocking(move || HubConnection::connect(&url)), server.accept());
+    let connection = Arc::new(connection.unwrap().unwrap());
+
+    // The handler holds the only handle left and drops it on the worker thread.
+    let slot = Arc::new(Mutex::new(Some(connection.clone())));
+    {
+        let slot = slot.clone();
+        connection.on("Quit", move |(): ()| drop(slot.lock().unwrap().take())).detach();
+    }
+    drop(connection);
+    session.invoke("Quit", ());
+    session.expect_close().await;
+    assert!(slot.lock().unwrap().is_none());
+}
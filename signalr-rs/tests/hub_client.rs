@@ -0,0 +1,97 @@
+use futures_util::{Stream, StreamExt};
+use signalr_rs::{error::ErrorKind, hub_client, HubConnectionBuilder, Result};
+
+#[hub_client]
+trait ChatHub {
+    async fn send_message(&self, user: String, message: String) -> Result<()>;
+
+    #[hub(send)]
+    async fn notify(&self, message: &str) -> Result<()>;
+
+    #[hub(name = "Add")]
+    async fn sum(&self, a: i32, b: i32) -> Result<i32>;
+
+    fn counter(&self, count: u32) -> impl Stream<Item = u32>;
+
+    fn checked_counter(&self, count: u32) -> impl Stream<Item = Result<u32>>;
+}
+
+fn disconnected() -> signalr_rs::HubConnection {
+    HubConnectionBuilder::new()
+        .with_url("http://localhost:5000/chat".to_owned())
+        .build()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn proxies_report_connection_state_errors() {
+    let connection = disconnected();
+
+    let error = connection.send_message("rust".to_owned(), "hello".to_owned()).await.unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidState);
+    let error = connection.notify("hello").await.unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidState);
+    let error = connection.sum(1, 2).await.unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidState);
+
+    assert_eq!(connection.counter(3).collect::<Vec<_>>().await, Vec::<u32>::new());
+    let items = connection.checked_counter(3).collect::<Vec<_>>().await;
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].as_ref().unwrap_err().kind(), ErrorKind::InvalidState);
+}
+
+#[cfg(feature = "testing")]
+#[tokio::test]
+async fn proxies_call_the_mock_hub() {
+    use serde_json::json;
+    use signalr_rs::testing::MockHubServer;
+
+    let mut server = MockHubServer::start().await.unwrap();
+    let connection = HubConnectionBuilder::new().with_url(server.url()).build().unwrap();
+    let (started, mut session) = tokio::join!(connection.start(), server.accept());
+    started.unwrap();
+
+    let (result, ()) = tokio::join!(connection.send_message("rust".to_owned(), "hello".to_owned()), async {
+        let invocation = session.expect_invocation("SendMessage").await;
+        assert_eq!(invocation.arguments, vec![json!("rust"), json!("hello")]);
+        session.complete(&invocation, json!(null));
+    });
+    result.unwrap();
+
+    connection.notify("hello").await.unwrap();
+    let invocation = session.expect_invocation("Notify").await;
+    assert_eq!((invocation.invocation_id, invocation.arguments), (None, vec![json!("hello")]));
+
+    let (result, ()) = tokio::join!(connection.sum(1, 2), async {
+        let invocation = session.expect_invocation("Add").await;
+        assert_eq!(invocation.arguments, vec![json!(1), json!(2)]);
+        session.complete(&invocation, json!(3));
+    });
+    assert_eq!(result.unwrap(), 3);
+    let (result, ()) = tokio::join!(connection.sum(1, 2), async {
+        let invocation = session.expect_invocation("Add").await;
+        session.complete(&invocation, json!("three"));
+    });
+    assert_eq!(result.unwrap_err().kind(), ErrorKind::Protocol);
+
+    let (items, ()) = tokio::join!(connection.counter(2).collect::<Vec<_>>(), async {
+        let invocation = session.expect_stream_invocation("Counter").await;
+        assert_eq!(invocation.arguments, vec![json!(2)]);
+        session.stream_item(&invocation, json!(0));
+        session.stream_item(&invocation, json!(1));
+        session.complete(&invocation, json!(null));
+    });
+    assert_eq!(items, vec![0, 1]);
+
+    let (items, ()) = tokio::join!(connection.checked_counter(2).collect::<Vec<_>>(), async {
+        let invocation = session.expect_stream_invocation("CheckedCounter").await;
+        session.stream_item(&invocation, json!(0));
+        session.stream_item(&invocation, json!("one"));
+        session.complete(&invocation, json!(null));
+    });
+    assert_eq!(items.len(), 2);
+    assert_eq!(*items[0].as_ref().unwrap(), 0);
+    assert_eq!(items[1].as_ref().unwrap_err().kind(), ErrorKind::Protocol);
+
+    connection.stop().await.unwrap();
+}
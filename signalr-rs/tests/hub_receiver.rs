@@ -0,0 +1,95 @@
+use std::sync::{Arc, Mutex};
+
+use serde_json::json;
+use signalr_rs::{error::ErrorKind, hub_receiver, HubReceiver};
+
+#[hub_receiver]
+trait ChatReceiver {
+    async fn receive_message(&self, user: String, message: String);
+
+    #[hub(name = "Tick")]
+    async fn on_tick(&self, count: u32);
+}
+
+#[derive(Default)]
+struct Recorder {
+    calls: Mutex<Vec<String>>,
+}
+
+#[hub_receiver]
+impl ChatReceiver for Recorder {
+    async fn receive_message(&self, user: String, message: String) {
+        self.calls.lock().unwrap().push(format!("{}: {}", user, message));
+    }
+
+    async fn on_tick(&self, count: u32) {
+        self.calls.lock().unwrap().push(format!("tick {}", count));
+    }
+}
+
+#[tokio::test]
+async fn receivers_check_arguments_before_calling() {
+    let recorder = Arc::new(Recorder::default());
+    let handlers = recorder.clone().handlers();
+    let targets: Vec<_> = handlers.iter().map(|handler| handler.target()).collect();
+    assert_eq!(targets, vec!["ReceiveMessage", "Tick"]);
+
+    handlers[0].call(vec![json!("rust"), json!("hello")]).unwrap().await;
+    handlers[1].call(vec![json!(3)]).unwrap().await;
+    assert_eq!(*recorder.calls.lock().unwrap(), vec!["rust: hello", "tick 3"]);
+
+    let error = handlers[0].call(vec![json!("rust")]).err().unwrap();
+    assert_eq!(error.kind(), ErrorKind::InvalidArguments);
+    let error = handlers[1].call(vec![json!("three")]).err().unwrap();
+    assert_eq!(error.kind(), ErrorKind::InvalidArguments);
+}
+
+/// Forwards every call to the test, over a connection to the mock hub.
+#[cfg(feature = "testing")]
+struct Forwarder {
+    calls: tokio::sync::mpsc::UnboundedSender<String>,
+}
+
+#[cfg(feature = "testing")]
+#[hub_receiver]
+impl ChatReceiver for Forwarder {
+    async fn receive_message(&self, user: String, message: String) {
+        let _ = self.calls.send(format!("{}: {}", user, message));
+    }
+
+    async fn on_tick(&self, count: u32) {
+        let _ = self.calls.send(format!("tick {}", count));
+    }
+}
+
+#[cfg(feature = "testing")]
+#[tokio::test]
+async fn registered_receivers_handle_server_invocations() {
+    use std::time::Duration;
+
+    use signalr_rs::{testing::MockHubServer, HubConnectionBuilder};
+    use tokio::{sync::mpsc, time};
+
+    let mut server = MockHubServer::start().await.unwrap();
+    let connection = HubConnectionBuilder::new().with_url(server.url()).build().unwrap();
+    let (calls, mut received) = mpsc::unbounded_channel();
+    connection.register(Forwarder { calls });
+    let (errors_tx, mut errors) = mpsc::unbounded_channel();
+    connection.on_handler_error(move |error| {
+        let _ = errors_tx.send(error);
+    });
+    let (started, session) = tokio::join!(connection.start(), server.accept());
+    started.unwrap();
+
+    session.invoke("ReceiveMessage", ("rust", "hello"));
+    session.invoke("Tick", ("three",));
+    session.invoke("Tick", (4,));
+    let timeout = Duration::from_secs(5);
+    assert_eq!(time::timeout(timeout, received.recv()).await.unwrap().unwrap(), "rust: hello");
+    let error = time::timeout(timeout, errors.recv()).await.unwrap().unwrap();
+    assert_eq!(error.kind(), ErrorKind::InvalidArguments);
+    assert_eq!(time::timeout(timeout, received.recv()).await.unwrap().unwrap(), "tick 4");
+    assert!(errors.try_recv().is_err());
+
+    connection.stop().await.unwrap();
+}